@@ -0,0 +1,185 @@
+//! Builds `assets/` from `asset_src/`.
+//!
+//! Every ball SVG is rasterised at each of [`QUALITIES`] and packed into that quality's atlas, the
+//! background SVG at the size the game draws it, and every WAV becomes an OGG. Anything without a source (the font, the
+//! foreground, sounds made elsewhere) has to already be in the output. Finally the full list goes
+//! into [`MANIFEST`] for the service worker. If anything the game loads is missing, it says so and
+//! exits non-zero.
+//!
+//! Usage: `asset-pipeline [SRC_DIR] [OUT_DIR]`, defaulting to `asset_src` and `assets`.
+
+use std::{
+    fs,
+    num::{NonZeroU32, NonZeroU8},
+    path::{Path, PathBuf},
+};
+
+use pumpkin_game::{
+    assets::{self, ATLAS_COLUMNS, BACKGROUND, MANIFEST, QUALITIES},
+    sim::BALL_ORDER,
+};
+use resvg::{
+    tiny_skia::{Pixmap, PixmapPaint, Transform},
+    usvg::{self, TreeParsing},
+};
+
+/// The background is drawn 4.8 x 7.2 world units, which is 480 x 720 px of SVG at 100 px per
+/// unit; render it at 1.5x for high density screens.
+const BACKGROUND_SCALE: f32 = 1.5;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let src = PathBuf::from(args.next().unwrap_or_else(|| "asset_src".into()));
+    let out = PathBuf::from(args.next().unwrap_or_else(|| "assets".into()));
+
+    if let Err(e) = fs::create_dir_all(&out) {
+        eprintln!("couldn't create {}: {}", out.display(), e);
+        std::process::exit(1);
+    }
+
+    let mut errors = Vec::new();
+
+    for &quality in QUALITIES {
+        let png = out.join(assets::ball_atlas(quality));
+        report(&mut errors, &png, atlas(&src, quality, &png));
+    }
+
+    let background = out.join(BACKGROUND);
+    report(
+        &mut errors,
+        &background,
+        render(&src.join(BACKGROUND.replace(".png", ".svg")), |w, h| {
+            (
+                (w * BACKGROUND_SCALE) as usize,
+                (h * BACKGROUND_SCALE) as usize,
+            )
+        })
+        .and_then(|pixmap| save(&pixmap, &background)),
+    );
+
+    for sound in assets::SOUNDS {
+        let wav = src.join(sound.replace(".ogg", ".wav"));
+        if wav.exists() {
+            let ogg = out.join(sound);
+            report(&mut errors, &ogg, encode(&wav, &ogg));
+        }
+    }
+
+    let files = assets::all();
+    for file in &files {
+        if !out.join(file).exists() {
+            errors.push(format!("{} is missing and has no source", file));
+        }
+    }
+
+    if !errors.is_empty() {
+        for e in &errors {
+            eprintln!("error: {}", e);
+        }
+        std::process::exit(1);
+    }
+
+    let manifest = out.join(MANIFEST);
+    let json = serde_json::to_string_pretty(&files).expect("a list of names always serialises");
+    if let Err(e) = fs::write(&manifest, json + "\n") {
+        eprintln!("couldn't write {}: {}", manifest.display(), e);
+        std::process::exit(1);
+    }
+    println!("wrote {} assets and {}", files.len(), manifest.display());
+}
+
+fn report(errors: &mut Vec<String>, path: &Path, result: Result<(), String>) {
+    match result {
+        Ok(()) => println!("{}", path.display()),
+        Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+    }
+}
+
+/// Renders every ball at `quality` into one image, laid out as [`assets::atlas_offset`] says.
+fn atlas(src: &Path, quality: usize, png: &Path) -> Result<(), String> {
+    let width = ATLAS_COLUMNS * quality;
+    let height = assets::atlas_rows() * quality;
+    let mut atlas = Pixmap::new(width as u32, height as u32)
+        .ok_or_else(|| format!("can't make a {}x{} image", width, height))?;
+
+    for (size, name) in BALL_ORDER.iter().enumerate() {
+        let tile = render(&src.join(format!("{}.svg", name)), |_, _| {
+            (quality, quality)
+        })?;
+        let (x, y) = assets::atlas_offset(size, quality);
+        atlas.draw_pixmap(
+            x as i32,
+            y as i32,
+            tile.as_ref(),
+            &PixmapPaint::default(),
+            Transform::identity(),
+            None,
+        );
+    }
+
+    save(&atlas, png)
+}
+
+fn save(pixmap: &Pixmap, png: &Path) -> Result<(), String> {
+    pixmap.save_png(png).map_err(|e| e.to_string())
+}
+
+/// Rasterises `svg` at the size `size` picks from the SVG's own.
+fn render(svg: &Path, size: impl Fn(f32, f32) -> (usize, usize)) -> Result<Pixmap, String> {
+    let data = fs::read(svg).map_err(|e| format!("reading {}: {}", svg.display(), e))?;
+    let mut tree = usvg::Tree::from_data(&data, &usvg::Options::default())
+        .map_err(|e| format!("parsing {}: {}", svg.display(), e))?;
+    tree.calculate_bounding_boxes();
+
+    let (width, height) = size(tree.size.width(), tree.size.height());
+    let mut pixmap = Pixmap::new(width as u32, height as u32)
+        .ok_or_else(|| format!("can't make a {}x{} image", width, height))?;
+    let transform = Transform::from_scale(
+        width as f32 / tree.size.width(),
+        height as f32 / tree.size.height(),
+    );
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    Ok(pixmap)
+}
+
+/// Re-encodes a WAV as Ogg Vorbis.
+fn encode(wav: &Path, ogg: &Path) -> Result<(), String> {
+    let mut reader =
+        hound::WavReader::open(wav).map_err(|e| format!("reading {}: {}", wav.display(), e))?;
+    let spec = reader.spec();
+
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>(),
+        hound::SampleFormat::Int => {
+            let max = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / max))
+                .collect::<Result<_, _>>()
+        }
+    }
+    .map_err(|e| format!("reading {}: {}", wav.display(), e))?;
+
+    let channels = spec.channels as usize;
+    let mut planar = vec![Vec::with_capacity(samples.len() / channels); channels];
+    for frame in samples.chunks(channels) {
+        for (channel, sample) in planar.iter_mut().zip(frame) {
+            channel.push(*sample);
+        }
+    }
+
+    let rate = NonZeroU32::new(spec.sample_rate).ok_or("sample rate is zero")?;
+    let count = NonZeroU8::new(spec.channels as u8).ok_or("no channels")?;
+    let file = fs::File::create(ogg).map_err(|e| e.to_string())?;
+
+    let mut encoder = vorbis_rs::VorbisEncoderBuilder::new(rate, count, file)
+        .and_then(|mut builder| builder.build())
+        .map_err(|e| e.to_string())?;
+    encoder
+        .encode_audio_block(&planar)
+        .map_err(|e| e.to_string())?;
+    encoder.finish().map_err(|e| e.to_string())?;
+
+    Ok(())
+}
@@ -0,0 +1,471 @@
+//! Reference leaderboard server.
+//!
+//! Every submitted score is checked by re-simulating its replay before it goes on the board, so
+//! the only way onto it is to actually play. Scores are kept in a JSON file next to the server.
+//!
+//! Replays are simulated on their own threads, a few at a time, so a long one doesn't hold up
+//! anyone else. One that takes too long is turned away. Each replay only goes on the board once,
+//! whatever name it's sent under.
+//!
+//! Usage: `leaderboard-server [ADDR] [SCORES_FILE]`, defaulting to `127.0.0.1:8787` and
+//! `scores.json`.
+
+use std::{
+    fs,
+    io::Read,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use pumpkin_game::{
+    leaderboard::{self, LeaderboardEntry, Submission, SubmitResponse, MAX_NAME_LEN},
+    replay::{self, Replay, ReplayOutcome},
+    sim::GameMode,
+};
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+const MAX_BODY: u64 = 1024 * 1024;
+const TOP_N: usize = 20;
+/// Replays being simulated at once. Any more are asked to try again later.
+const MAX_SIMULATIONS: usize = 4;
+/// Longest a submitter is kept waiting for their replay to be checked. A full board simulates at
+/// about 1200 ticks a second, so the longest replay allowed, [`replay::MAX_REPLAY_TICKS`], takes
+/// about half of this.
+const SIMULATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// An accepted score, with what it was earned by.
+#[derive(Serialize, Deserialize)]
+struct Stored {
+    #[serde(flatten)]
+    entry: LeaderboardEntry,
+    /// [`fingerprint`] of the replay. Missing from scores kept before there was one.
+    #[serde(default)]
+    replay: Option<String>,
+}
+
+struct Board {
+    path: PathBuf,
+    entries: Vec<Stored>,
+}
+
+impl Board {
+    fn load(path: PathBuf) -> Self {
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+
+        Board { path, entries }
+    }
+
+    fn save(&self) {
+        match serde_json::to_string_pretty(&self.entries) {
+            Ok(s) => {
+                if let Err(e) = fs::write(&self.path, s) {
+                    eprintln!("couldn't write {}: {}", self.path.display(), e);
+                }
+            }
+            Err(e) => eprintln!("couldn't serialise scores: {}", e),
+        }
+    }
+
+    fn has_replay(&self, fingerprint: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|e| e.replay.as_deref() == Some(fingerprint))
+    }
+
+    /// Adds an entry and returns its 1-based rank within its mode.
+    fn insert(&mut self, entry: LeaderboardEntry, fingerprint: String) -> usize {
+        let mode = entry.mode;
+        let score = entry.score;
+        self.entries.push(Stored {
+            entry,
+            replay: Some(fingerprint),
+        });
+        self.entries
+            .sort_by_key(|e| std::cmp::Reverse(e.entry.score));
+        self.save();
+
+        self.entries
+            .iter()
+            .filter(|e| e.entry.mode == mode && e.entry.score > score)
+            .count()
+            + 1
+    }
+
+    fn top(&self, mode: GameMode) -> Vec<&LeaderboardEntry> {
+        self.entries
+            .iter()
+            .map(|e| &e.entry)
+            .filter(|e| e.mode == mode)
+            .take(TOP_N)
+            .collect()
+    }
+}
+
+/// What the server shares between the threads answering requests.
+#[derive(Clone)]
+struct Shared {
+    board: Arc<Mutex<Board>>,
+    /// Replays being simulated right now, including any whose submitter gave up waiting.
+    simulating: Arc<AtomicUsize>,
+}
+
+/// Counts a simulation as running for as long as it's held.
+struct SimulationSlot(Arc<AtomicUsize>);
+
+impl SimulationSlot {
+    fn take(simulating: &Arc<AtomicUsize>) -> Option<Self> {
+        simulating
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                (n < MAX_SIMULATIONS).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| SimulationSlot(simulating.clone()))
+    }
+}
+
+impl Drop for SimulationSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let addr = args.next().unwrap_or_else(|| "127.0.0.1:8787".into());
+    let path = PathBuf::from(args.next().unwrap_or_else(|| "scores.json".into()));
+
+    let server = match Server::http(&addr) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("couldn't listen on {}: {}", addr, e);
+            std::process::exit(1);
+        }
+    };
+    let shared = Shared {
+        board: Arc::new(Mutex::new(Board::load(path))),
+        simulating: Arc::default(),
+    };
+
+    println!("leaderboard listening on http://{}", addr);
+
+    for request in server.incoming_requests() {
+        // submissions can take a while to check, so each gets its own thread
+        if *request.method() == Method::Post {
+            let shared = shared.clone();
+            thread::spawn(move || handle(request, &shared));
+        } else {
+            handle(request, &shared);
+        }
+    }
+}
+
+fn handle(mut request: Request, shared: &Shared) {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+
+    let (status, body) = match (request.method(), path) {
+        // CORS preflight from the web build
+        (Method::Options, _) => (204, String::new()),
+        (Method::Get, "/scores") => {
+            let mode = query
+                .split('&')
+                .find_map(|kv| kv.strip_prefix("mode="))
+                .and_then(|m| serde_json::from_value(m.into()).ok())
+                .unwrap_or_default();
+            let board = shared.board.lock().unwrap();
+            (200, to_json(&board.top(mode)))
+        }
+        (Method::Post, "/scores") => {
+            let mut raw = String::new();
+            let read = request.as_reader().take(MAX_BODY).read_to_string(&mut raw);
+
+            let response = match read {
+                Ok(_) => match serde_json::from_str::<Submission>(&raw) {
+                    Ok(submission) => submit(submission, shared),
+                    Err(e) => SubmitResponse::Rejected {
+                        reason: format!("bad submission: {}", e),
+                    },
+                },
+                Err(e) => SubmitResponse::Rejected {
+                    reason: format!("couldn't read body: {}", e),
+                },
+            };
+            (200, to_json(&response))
+        }
+        _ => (404, String::new()),
+    };
+
+    let response = Response::from_string(body)
+        .with_status_code(status)
+        .with_header(header("Content-Type", "application/json"))
+        .with_header(header("Access-Control-Allow-Origin", "*"))
+        .with_header(header("Access-Control-Allow-Methods", "GET, POST, OPTIONS"))
+        .with_header(header("Access-Control-Allow-Headers", "Content-Type"));
+
+    if let Err(e) = request.respond(response) {
+        eprintln!("couldn't respond: {}", e);
+    }
+}
+
+fn submit(submission: Submission, shared: &Shared) -> SubmitResponse {
+    let name: String = submission.name.trim().chars().take(MAX_NAME_LEN).collect();
+    if name.is_empty() {
+        return SubmitResponse::Rejected {
+            reason: "name is empty".into(),
+        };
+    }
+
+    if !leaderboard::is_ranked(&submission.replay) {
+        return SubmitResponse::Rejected {
+            reason: "this kind of run isn't ranked".into(),
+        };
+    }
+
+    let fingerprint = fingerprint(&submission.replay);
+    if shared.board.lock().unwrap().has_replay(&fingerprint) {
+        return SubmitResponse::Rejected {
+            reason: "already submitted".into(),
+        };
+    }
+
+    let outcome = match simulate(submission.replay.clone(), &shared.simulating) {
+        Ok(Ok(outcome)) => outcome,
+        Ok(Err(e)) => {
+            return SubmitResponse::Rejected {
+                reason: e.to_string(),
+            }
+        }
+        Err(reason) => return SubmitResponse::Rejected { reason },
+    };
+
+    if outcome.score != submission.score {
+        println!(
+            "rejected {} claiming {}, replay scored {}",
+            name, submission.score, outcome.score
+        );
+        return SubmitResponse::Rejected {
+            reason: format!("replay scored {}", outcome.score),
+        };
+    }
+
+    let mut board = shared.board.lock().unwrap();
+    // the same replay may have come in twice at once
+    if board.has_replay(&fingerprint) {
+        return SubmitResponse::Rejected {
+            reason: "already submitted".into(),
+        };
+    }
+
+    println!("accepted {} with {}", name, outcome.score);
+
+    let entry = LeaderboardEntry {
+        name,
+        score: outcome.score,
+        seed: submission.replay.seed,
+        mode: submission.replay.mode,
+    };
+    let rank = board.insert(entry, fingerprint);
+
+    SubmitResponse::Accepted { rank }
+}
+
+/// Re-runs `replay` on a thread of its own, giving up on it after [`SIMULATION_TIMEOUT`]. The
+/// outer error is why it wasn't checked.
+fn simulate(
+    replay: Replay,
+    simulating: &Arc<AtomicUsize>,
+) -> Result<Result<ReplayOutcome, replay::ReplayError>, String> {
+    let Some(slot) = SimulationSlot::take(simulating) else {
+        return Err("server busy, try again later".into());
+    };
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        // the slot stays taken until the simulation finishes, even if nobody's waiting for it
+        let _slot = slot;
+        let _ = tx.send(replay::simulate(&replay));
+    });
+
+    rx.recv_timeout(SIMULATION_TIMEOUT)
+        .map_err(|_| "replay took too long to check".to_string())
+}
+
+/// Identifies a replay by its seed, mode, drops and tilts, so the same run can't be entered twice
+/// under different names. FNV-1a over the JSON, which stays the same between builds.
+fn fingerprint(replay: &Replay) -> String {
+    let inputs = (replay.seed, replay.mode, &replay.drops, &replay.tilts);
+    let hash = serde_json::to_vec(&inputs)
+        .unwrap_or_default()
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    format!("{:016x}", hash)
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("static header is valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pumpkin_game::{level::Level, replay::ReplayDrop};
+
+    /// A board of its own, kept in a scratch file.
+    fn shared(name: &str) -> Shared {
+        let path = std::env::temp_dir().join(format!(
+            "pumpkin-leaderboard-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        Shared {
+            board: Arc::new(Mutex::new(Board::load(path))),
+            simulating: Arc::default(),
+        }
+    }
+
+    /// A short run, dropping every second and a half across the box, and what it really scored.
+    fn played(seed: u64) -> (Replay, usize) {
+        let mut replay = Replay::new(seed, GameMode::Classic);
+        replay.drops = (0..12)
+            .map(|i| ReplayDrop {
+                tick: 90 * (i + 1),
+                x: (i % 5) as f32 * 0.6 - 1.2,
+            })
+            .collect();
+        replay.end_tick = 90 * 14;
+        let score = replay::simulate(&replay).unwrap().score;
+        (replay, score)
+    }
+
+    fn submission(name: &str, score: usize, replay: &Replay) -> Submission {
+        Submission {
+            name: name.into(),
+            score,
+            replay: replay.clone(),
+        }
+    }
+
+    fn entry(score: usize, mode: GameMode) -> LeaderboardEntry {
+        LeaderboardEntry {
+            name: "test".into(),
+            score,
+            seed: 0,
+            mode,
+        }
+    }
+
+    #[test]
+    fn scores_must_match_the_replay() {
+        let shared = shared("score");
+        let (replay, score) = played(1);
+
+        match submit(submission("me", score + 10, &replay), &shared) {
+            SubmitResponse::Rejected { reason } => {
+                assert_eq!(reason, format!("replay scored {}", score))
+            }
+            other => panic!("a wrong score got {:?}", other),
+        }
+        assert!(matches!(
+            submit(submission("me", score, &replay), &shared),
+            SubmitResponse::Accepted { rank: 1 }
+        ));
+    }
+
+    #[test]
+    fn a_replay_only_goes_on_once_whatever_its_name() {
+        let shared = shared("duplicate");
+        let (replay, score) = played(2);
+
+        assert!(matches!(
+            submit(submission("me", score, &replay), &shared),
+            SubmitResponse::Accepted { .. }
+        ));
+        assert!(matches!(
+            submit(submission("someone else", score, &replay), &shared),
+            SubmitResponse::Rejected { reason } if reason == "already submitted"
+        ));
+    }
+
+    #[test]
+    fn tutorials_are_turned_away() {
+        let shared = shared("tutorial");
+        let (mut replay, score) = played(3);
+        replay.mode = GameMode::Tutorial;
+
+        assert!(matches!(
+            submit(submission("me", score, &replay), &shared),
+            SubmitResponse::Rejected { reason } if reason == "this kind of run isn't ranked"
+        ));
+    }
+
+    #[test]
+    fn other_levels_are_turned_away() {
+        let shared = shared("level");
+        let (mut replay, score) = played(4);
+        replay.level = Level::built_in("pegs").unwrap();
+
+        assert!(matches!(
+            submit(submission("me", score, &replay), &shared),
+            SubmitResponse::Rejected { reason } if reason == "this kind of run isn't ranked"
+        ));
+    }
+
+    #[test]
+    fn fingerprints_follow_the_inputs() {
+        let (replay, _) = played(3);
+        assert_eq!(fingerprint(&replay), fingerprint(&replay.clone()));
+
+        let mut moved = replay.clone();
+        moved.drops[0].x += 0.1;
+        assert_ne!(fingerprint(&replay), fingerprint(&moved));
+
+        let mut reseeded = replay.clone();
+        reseeded.seed += 1;
+        assert_ne!(fingerprint(&replay), fingerprint(&reseeded));
+    }
+
+    #[test]
+    fn entries_are_ranked_by_score() {
+        let shared = shared("rank");
+        let mut board = shared.board.lock().unwrap();
+
+        assert_eq!(board.insert(entry(100, GameMode::Classic), "a".into()), 1);
+        assert_eq!(board.insert(entry(300, GameMode::Classic), "b".into()), 1);
+        assert_eq!(board.insert(entry(200, GameMode::Classic), "c".into()), 2);
+        assert_eq!(board.insert(entry(250, GameMode::Chaos), "d".into()), 1);
+        assert_eq!(board.insert(entry(50, GameMode::Chaos), "e".into()), 2);
+        let scores = |mode| -> Vec<usize> { board.top(mode).iter().map(|e| e.score).collect() };
+        assert_eq!(scores(GameMode::Classic), [300, 200, 100]);
+        assert_eq!(scores(GameMode::Chaos), [250, 50]);
+    }
+
+    #[test]
+    fn simulation_slots_are_capped_and_given_back() {
+        let simulating = Arc::default();
+        let mut slots: Vec<_> = (0..MAX_SIMULATIONS)
+            .map(|_| SimulationSlot::take(&simulating).expect("a free slot"))
+            .collect();
+        assert!(SimulationSlot::take(&simulating).is_none());
+
+        slots.pop();
+        assert!(SimulationSlot::take(&simulating).is_some());
+        drop(slots);
+        assert_eq!(simulating.load(Ordering::SeqCst), 0);
+    }
+}
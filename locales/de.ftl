@@ -0,0 +1,111 @@
+language-name = Deutsch
+
+title = Pumpkin Game!
+continue = Weiter
+play = Spielen
+music = Musik
+sounds = Töne
+achievements = Erfolge
+stats = Statistik
+back = Zurück
+
+how-to-play = Anleitung
+help-drop = Bewegen, dann klicken oder tippen, um die nächste Kugel fallen zu lassen
+help-merge = Zwei gleiche verschmelzen zur nächstgrößeren
+help-line = Lass den Stapel nicht über den Rand wachsen
+help-combo = Verschmelzungen weniger als { $window } s auseinander ergeben eine Kombo. Jede zählt ihre Größe mal ihren Platz in der Kombo, und Kombos ab 2 bringen am Ende { $bonus } pro Verschmelzung
+help-order = Von klein nach groß:
+
+tutorial = Tutorial
+skip = Überspringen
+tutorial-drop = Bewegen zum Zielen, dann klicken oder tippen zum Fallenlassen
+tutorial-merge = Lass das nächste Bonbon auf das erste fallen: zwei gleiche verschmelzen zur nächsten Größe
+tutorial-combo = Verschmilz erneut, bevor die Anzeige leer ist, für eine Kombo, in der jede Verschmelzung mehr zählt. Staple Bonbons neben der Spinne
+tutorial-line = Bleibt etwas zu lange über dieser Linie, ist das Spiel vorbei
+tutorial-done = Das war's! Jetzt versuch, einen Kürbis zu machen
+
+challenges = Aufgaben
+goal-make = Ziel: { $ball }
+goal-clear = Räume ab: { $ball }
+goal-score = { $points } Punkte erreichen
+balls-left = Noch { $balls }
+challenge-complete = Aufgabe geschafft!
+challenge-failed = Aufgabe verfehlt
+
+chaos = Chaos
+modifier-swirl = Die Schwerkraft schwankt!
+modifier-moon = Mondschwerkraft!
+modifier-flip = Kopfüber!
+modifier-tilt = Kipp die Kiste: neig dein Handy oder nutz die Pfeiltasten
+modifier-over = Die Schwerkraft ist wieder normal
+
+a11y = Barrierefreiheit
+a11y-high-contrast = Hoher Kontrast: { $state }
+a11y-reduced-motion = Weniger Bewegung: { $state }
+a11y-tier-numbers = Stufennummern: { $state }
+a11y-text-size = Textgröße: { $size } %
+on = An
+off = Aus
+
+score = Punkte: { $score }
+hint = Tipp
+next = Nächster:
+combo = x{ $multiplier }
+combo-bonus = Kombo x{ $merges }! +{ $bonus }
+
+game-over = Pech gehabt
+play-again = Nochmal
+submit-score = Senden
+submitting = Wird gesendet...
+ranked = Platz #{ $rank }!
+rejected = Abgelehnt: { $reason }
+unreachable = Bestenliste nicht erreichbar: { $error }
+
+run-time = Zeit: { $time }
+run-drops = Würfe: { $drops }
+run-chain = Beste Kette: { $chain }x
+run-biggest = Größter: { $ball }
+run-made = Gemacht: { $balls }
+
+stats-heading = Statistik
+stat-games-played = Spiele
+stat-best-score = Bestwert
+stat-total-score = Punkte gesamt
+stat-drops = Würfe
+stat-merges = Verschmelzungen
+stat-pumpkins = Kürbisse gemacht
+stat-best-chain = Beste Kette
+stat-time-played = Spielzeit
+export = Exportieren
+export-saved = Gespeichert: { $files }
+export-failed = Export fehlgeschlagen
+
+achievements-heading = Erfolge { $unlocked }/{ $total }
+achievement-unlocked = Erfolg freigeschaltet!
+achievement-locked = (gesperrt) { $name }
+achievement-first_merge = Matsch
+achievement-first_merge-desc = Verschmilz zwei Kugeln
+achievement-skull = Knochensammler
+achievement-skull-desc = Mach einen Schädel
+achievement-pumpkin = Kürbiskönig
+achievement-pumpkin-desc = Mach einen Kürbis
+achievement-chain5 = Kettenreaktion
+achievement-chain5-desc = Erreiche einen 5x-Multiplikator
+achievement-right_handed = Rechtshänder
+achievement-right_handed-desc = 1000 Punkte, ohne in der linken Hälfte zu werfen
+achievement-unassisted = Ohne Hilfe
+achievement-unassisted-desc = 1000 Punkte ohne Tipp
+achievement-score2000 = Kürbisbeet
+achievement-score2000-desc = 2000 Punkte in einem Spiel
+
+ball-sweet = Bonbon
+ball-spider = Spinne
+ball-bat = Fledermaus
+ball-apple = Apfel
+ball-candy_apple = Liebesapfel
+ball-ghost = Geist
+ball-vampire = Vampir
+ball-mummy = Mumie
+ball-frankenstein = Frankenstein
+ball-skull = Schädel
+ball-pumpkin = Kürbis
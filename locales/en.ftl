@@ -0,0 +1,114 @@
+# Every piece of text the player can see. Other languages fall back to these for anything they
+# don't have.
+
+language-name = English
+
+title = Pumpkin Game!
+continue = Continue
+play = Play
+music = Music
+sounds = Sounds
+achievements = Achievements
+stats = Stats
+back = Back
+
+how-to-play = How to Play
+help-drop = Move, then click or tap to drop the next ball
+help-merge = Two of the same merge into the next one up
+help-line = Don't let the pile go over the top
+help-combo = Merges less than { $window }s apart make a combo. Each scores its size times how far into the combo it is, and combos of 2 or more pay { $bonus } a merge when they end
+help-order = Smallest to biggest:
+
+tutorial = Tutorial
+skip = Skip
+tutorial-drop = Move to aim, then click or tap to drop
+tutorial-merge = Drop the next sweet on the first: two the same merge into the next size up
+tutorial-combo = Merge again before the meter runs out to build a combo, where every merge scores more. Try stacking sweets beside the spider
+tutorial-line = If anything stays above this line for too long, it's game over
+tutorial-done = That's it! Now try to make a pumpkin
+
+challenges = Challenges
+goal-make = Make the { $ball }
+goal-clear = Clear every { $ball }
+goal-score = Score { $points }
+balls-left = { $balls } left
+challenge-complete = Challenge complete!
+challenge-failed = Challenge failed
+
+chaos = Chaos
+modifier-swirl = Gravity's swinging!
+modifier-moon = Moon gravity!
+modifier-flip = Upside down!
+modifier-tilt = Tilt the box: tip your phone or use the arrow keys
+modifier-over = Gravity's back to normal
+
+a11y = Accessibility
+a11y-high-contrast = High contrast: { $state }
+a11y-reduced-motion = Reduced motion: { $state }
+a11y-tier-numbers = Tier numbers: { $state }
+a11y-text-size = Text size: { $size }%
+on = On
+off = Off
+
+score = Score: { $score }
+hint = Hint
+next = Next:
+combo = x{ $multiplier }
+combo-bonus = Combo x{ $merges }! +{ $bonus }
+
+game-over = Skill Issue
+play-again = Play Again
+submit-score = Submit Score
+submitting = Submitting...
+ranked = Ranked #{ $rank }!
+rejected = Rejected: { $reason }
+unreachable = Couldn't reach leaderboard: { $error }
+
+run-time = Time: { $time }
+run-drops = Drops: { $drops }
+run-chain = Best chain: { $chain }x
+run-biggest = Biggest: { $ball }
+run-made = Made: { $balls }
+
+stats-heading = Stats
+stat-games-played = Games played
+stat-best-score = Best score
+stat-total-score = Total score
+stat-drops = Drops
+stat-merges = Merges
+stat-pumpkins = Pumpkins made
+stat-best-chain = Best chain
+stat-time-played = Time played
+export = Export
+export-saved = Saved { $files }
+export-failed = Couldn't export
+
+achievements-heading = Achievements { $unlocked }/{ $total }
+achievement-unlocked = Achievement unlocked!
+achievement-locked = (locked) { $name }
+achievement-first_merge = Squish
+achievement-first_merge-desc = Merge two balls
+achievement-skull = Bone Collector
+achievement-skull-desc = Make a skull
+achievement-pumpkin = Pumpkin King
+achievement-pumpkin-desc = Make a pumpkin
+achievement-chain5 = Chain Reaction
+achievement-chain5-desc = Reach a 5x multiplier
+achievement-right_handed = Right-Handed
+achievement-right_handed-desc = Score 1000 without dropping in the left half
+achievement-unassisted = Unassisted
+achievement-unassisted-desc = Score 1000 without a hint
+achievement-score2000 = Pumpkin Patch
+achievement-score2000-desc = Score 2000 in one run
+
+ball-sweet = sweet
+ball-spider = spider
+ball-bat = bat
+ball-apple = apple
+ball-candy_apple = candy apple
+ball-ghost = ghost
+ball-vampire = vampire
+ball-mummy = mummy
+ball-frankenstein = frankenstein
+ball-skull = skull
+ball-pumpkin = pumpkin
@@ -0,0 +1,111 @@
+language-name = Español
+
+title = ¡Pumpkin Game!
+continue = Continuar
+play = Jugar
+music = Música
+sounds = Sonidos
+achievements = Logros
+stats = Datos
+back = Volver
+
+how-to-play = Cómo jugar
+help-drop = Mueve y haz clic o toca para soltar la siguiente bola
+help-merge = Dos iguales se unen en la siguiente
+help-line = No dejes que la pila pase del borde
+help-combo = Las uniones a menos de { $window } s unas de otras forman un combo. Cada una puntúa su tamaño por su puesto en el combo, y los combos de 2 o más dan { $bonus } por unión al terminar
+help-order = De la más pequeña a la más grande:
+
+tutorial = Tutorial
+skip = Saltar
+tutorial-drop = Muévete para apuntar y haz clic o toca para soltar
+tutorial-merge = Suelta el siguiente caramelo sobre el primero: dos iguales se unen en el siguiente tamaño
+tutorial-combo = Vuelve a unir antes de que se vacíe el medidor para hacer un combo, donde cada unión puntúa más. Prueba a apilar caramelos junto a la araña
+tutorial-line = Si algo se queda por encima de esta línea demasiado tiempo, se acaba la partida
+tutorial-done = ¡Eso es todo! Ahora intenta hacer una calabaza
+
+challenges = Desafíos
+goal-make = Consigue: { $ball }
+goal-clear = Quita cada { $ball }
+goal-score = Consigue { $points } puntos
+balls-left = Quedan { $balls }
+challenge-complete = ¡Desafío superado!
+challenge-failed = Desafío fallido
+
+chaos = Caos
+modifier-swirl = ¡La gravedad se balancea!
+modifier-moon = ¡Gravedad lunar!
+modifier-flip = ¡Boca abajo!
+modifier-tilt = Inclina la caja: mueve el móvil o usa las flechas
+modifier-over = La gravedad vuelve a la normalidad
+
+a11y = Accesibilidad
+a11y-high-contrast = Alto contraste: { $state }
+a11y-reduced-motion = Menos movimiento: { $state }
+a11y-tier-numbers = Números de nivel: { $state }
+a11y-text-size = Tamaño del texto: { $size }%
+on = Sí
+off = No
+
+score = Puntos: { $score }
+hint = Pista
+next = Sigue:
+combo = x{ $multiplier }
+combo-bonus = ¡Combo x{ $merges }! +{ $bonus }
+
+game-over = Fallaste
+play-again = Otra vez
+submit-score = Enviar
+submitting = Enviando...
+ranked = ¡Puesto #{ $rank }!
+rejected = Rechazado: { $reason }
+unreachable = No se pudo conectar: { $error }
+
+run-time = Tiempo: { $time }
+run-drops = Caídas: { $drops }
+run-chain = Mejor cadena: { $chain }x
+run-biggest = El más grande: { $ball }
+run-made = Hechos: { $balls }
+
+stats-heading = Datos
+stat-games-played = Partidas
+stat-best-score = Mejor puntuación
+stat-total-score = Puntos totales
+stat-drops = Caídas
+stat-merges = Fusiones
+stat-pumpkins = Calabazas hechas
+stat-best-chain = Mejor cadena
+stat-time-played = Tiempo jugado
+export = Exportar
+export-saved = Guardado { $files }
+export-failed = No se pudo exportar
+
+achievements-heading = Logros { $unlocked }/{ $total }
+achievement-unlocked = ¡Logro desbloqueado!
+achievement-locked = (bloqueado) { $name }
+achievement-first_merge = Aplastado
+achievement-first_merge-desc = Fusiona dos bolas
+achievement-skull = Coleccionista de huesos
+achievement-skull-desc = Haz una calavera
+achievement-pumpkin = Rey calabaza
+achievement-pumpkin-desc = Haz una calabaza
+achievement-chain5 = Reacción en cadena
+achievement-chain5-desc = Llega a un multiplicador de 5x
+achievement-right_handed = Diestro
+achievement-right_handed-desc = Haz 1000 puntos sin soltar en la mitad izquierda
+achievement-unassisted = Sin ayuda
+achievement-unassisted-desc = Haz 1000 puntos sin pistas
+achievement-score2000 = Huerto de calabazas
+achievement-score2000-desc = Haz 2000 puntos en una partida
+
+ball-sweet = caramelo
+ball-spider = araña
+ball-bat = murciélago
+ball-apple = manzana
+ball-candy_apple = manzana de caramelo
+ball-ghost = fantasma
+ball-vampire = vampiro
+ball-mummy = momia
+ball-frankenstein = frankenstein
+ball-skull = calavera
+ball-pumpkin = calabaza
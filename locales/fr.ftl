@@ -0,0 +1,111 @@
+language-name = Français
+
+title = Pumpkin Game !
+continue = Continuer
+play = Jouer
+music = Musique
+sounds = Sons
+achievements = Succès
+stats = Stats
+back = Retour
+
+how-to-play = Comment jouer
+help-drop = Bouge, puis clique ou touche pour lâcher la balle suivante
+help-merge = Deux pareilles fusionnent en la suivante
+help-line = Ne laisse pas la pile dépasser le haut
+help-combo = Des fusions à moins de { $window } s d'écart forment un combo. Chacune rapporte sa taille fois son rang dans le combo, et un combo de 2 ou plus rapporte { $bonus } par fusion à la fin
+help-order = De la plus petite à la plus grosse :
+
+tutorial = Tutoriel
+skip = Passer
+tutorial-drop = Bouge pour viser, puis clique ou touche pour lâcher
+tutorial-merge = Lâche le bonbon suivant sur le premier : deux pareils fusionnent en la taille au-dessus
+tutorial-combo = Fusionne encore avant que la jauge se vide pour faire un combo, où chaque fusion rapporte plus. Essaie d'empiler des bonbons à côté de l'araignée
+tutorial-line = Si quelque chose reste trop longtemps au-dessus de cette ligne, la partie est finie
+tutorial-done = C'est tout ! Essaie maintenant de faire une citrouille
+
+challenges = Défis
+goal-make = Objectif : { $ball }
+goal-clear = Élimine chaque { $ball }
+goal-score = Marque { $points } points
+balls-left = Encore { $balls }
+challenge-complete = Défi réussi !
+challenge-failed = Défi raté
+
+chaos = Chaos
+modifier-swirl = La gravité tangue !
+modifier-moon = Gravité lunaire !
+modifier-flip = La tête en bas !
+modifier-tilt = Penche la boîte : incline ton téléphone ou utilise les flèches
+modifier-over = La gravité revient à la normale
+
+a11y = Accessibilité
+a11y-high-contrast = Contraste élevé : { $state }
+a11y-reduced-motion = Moins d'animations : { $state }
+a11y-tier-numbers = Numéros de niveau : { $state }
+a11y-text-size = Taille du texte : { $size } %
+on = Oui
+off = Non
+
+score = Score : { $score }
+hint = Indice
+next = Suivant :
+combo = x{ $multiplier }
+combo-bonus = Combo x{ $merges } ! +{ $bonus }
+
+game-over = Raté
+play-again = Rejouer
+submit-score = Envoyer
+submitting = Envoi...
+ranked = Classé #{ $rank } !
+rejected = Refusé : { $reason }
+unreachable = Classement injoignable : { $error }
+
+run-time = Temps : { $time }
+run-drops = Lâchers : { $drops }
+run-chain = Meilleure chaîne : { $chain }x
+run-biggest = Le plus gros : { $ball }
+run-made = Créés : { $balls }
+
+stats-heading = Stats
+stat-games-played = Parties jouées
+stat-best-score = Meilleur score
+stat-total-score = Score total
+stat-drops = Lâchers
+stat-merges = Fusions
+stat-pumpkins = Citrouilles créées
+stat-best-chain = Meilleure chaîne
+stat-time-played = Temps de jeu
+export = Exporter
+export-saved = Enregistré { $files }
+export-failed = Export impossible
+
+achievements-heading = Succès { $unlocked }/{ $total }
+achievement-unlocked = Succès débloqué !
+achievement-locked = (verrouillé) { $name }
+achievement-first_merge = Écrabouillé
+achievement-first_merge-desc = Fusionner deux boules
+achievement-skull = Collectionneur d'os
+achievement-skull-desc = Créer un crâne
+achievement-pumpkin = Roi citrouille
+achievement-pumpkin-desc = Créer une citrouille
+achievement-chain5 = Réaction en chaîne
+achievement-chain5-desc = Atteindre un multiplicateur de 5x
+achievement-right_handed = Droitier
+achievement-right_handed-desc = Marquer 1000 sans lâcher dans la moitié gauche
+achievement-unassisted = Sans aide
+achievement-unassisted-desc = Marquer 1000 sans indice
+achievement-score2000 = Champ de citrouilles
+achievement-score2000-desc = Marquer 2000 en une partie
+
+ball-sweet = bonbon
+ball-spider = araignée
+ball-bat = chauve-souris
+ball-apple = pomme
+ball-candy_apple = pomme d'amour
+ball-ghost = fantôme
+ball-vampire = vampire
+ball-mummy = momie
+ball-frankenstein = frankenstein
+ball-skull = crâne
+ball-pumpkin = citrouille
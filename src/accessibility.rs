@@ -0,0 +1,377 @@
+//! Accessibility options, kept between sessions. Tiers are otherwise told apart only by their
+//! art, so balls can be outlined and numbered; UI text can be made bigger; and anything that
+//! shakes or bursts is meant to check `reduced_motion` first.
+
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+use pumpkin_game::sim::{BallSize, NextNextBallSize, SimParams, BALL_ORDER};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    layout::SafeArea, locale::Locale, spawn_button, storage, CustomFont, GameState, NextUpTag,
+};
+
+const STORAGE_KEY: &str = "accessibility";
+
+const TEXT_SCALES: &[f32] = &[1.0, 1.25, 1.5];
+/// How far the high contrast outline sticks out past a ball, in world units.
+const OUTLINE_WIDTH: f32 = 0.04;
+/// Tier numbers are laid out at this size, then scaled down into world units.
+const BADGE_FONT_SIZE: f32 = 64.0;
+
+pub struct AccessibilityPlugin;
+
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(storage::load::<Accessibility>(STORAGE_KEY).unwrap_or_default())
+            .add_systems(Startup, build_outlines)
+            .add_systems(
+                Update,
+                (
+                    accessibility_button,
+                    decorate_balls,
+                    add_next_up_badge,
+                    update_next_up_badge,
+                ),
+            )
+            .add_systems(OnEnter(GameState::Accessibility), build_accessibility)
+            .add_systems(
+                OnExit(GameState::Accessibility),
+                crate::despawn_with::<AccessibilityTag>,
+            )
+            .add_systems(
+                Update,
+                (option_buttons, update_labels, back_button)
+                    .run_if(in_state(GameState::Accessibility)),
+            );
+    }
+}
+
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Accessibility {
+    /// Outlines and numbers every ball.
+    pub high_contrast: bool,
+    /// No screen shake, bursts or other movement that isn't the game itself.
+    pub reduced_motion: bool,
+    /// Multiplies the size of all UI on top of fitting it to the screen, one of [`TEXT_SCALES`].
+    pub text_scale: f32,
+    /// Numbers every ball, and the next-up preview, by tier.
+    pub tier_numbers: bool,
+}
+
+impl Default for Accessibility {
+    fn default() -> Self {
+        Accessibility {
+            high_contrast: false,
+            reduced_motion: prefers_reduced_motion(),
+            text_scale: 1.0,
+            tier_numbers: false,
+        }
+    }
+}
+
+impl Accessibility {
+    pub fn show_tiers(&self) -> bool {
+        self.tier_numbers || self.high_contrast
+    }
+}
+
+/// Follows the browser's setting until the player picks for themselves.
+#[cfg(target_arch = "wasm32")]
+fn prefers_reduced_motion() -> bool {
+    web_sys::window()
+        .and_then(|window| {
+            window
+                .match_media("(prefers-reduced-motion: reduce)")
+                .ok()?
+        })
+        .is_some_and(|query| query.matches())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn prefers_reduced_motion() -> bool {
+    false
+}
+
+/// A circle a little bigger than each size of ball, drawn behind it in high contrast mode.
+#[derive(Resource)]
+struct Outlines {
+    meshes: Vec<Handle<Mesh>>,
+    material: Handle<ColorMaterial>,
+}
+
+fn build_outlines(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    params: Res<SimParams>,
+) {
+    commands.insert_resource(Outlines {
+        meshes: (0..BALL_ORDER.len())
+            .map(|size| meshes.add(shape::Circle::new(params.radius(size) + OUTLINE_WIDTH).into()))
+            .collect(),
+        material: materials.add(ColorMaterial::from(Color::WHITE)),
+    });
+}
+
+/// Anything hung off a ball by these options, so it can all be taken off when they change.
+#[derive(Component)]
+struct Decoration;
+
+fn decorate_balls(
+    added_q: Query<(Entity, &BallSize), Added<BallSize>>,
+    ball_q: Query<(Entity, &BallSize)>,
+    decoration_q: Query<Entity, With<Decoration>>,
+    settings: Res<Accessibility>,
+    outlines: Res<Outlines>,
+    params: Res<SimParams>,
+    mut commands: Commands,
+) {
+    let balls: Vec<(Entity, &BallSize)> = if settings.is_changed() {
+        for entity in decoration_q.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        ball_q.iter().collect()
+    } else {
+        added_q.iter().collect()
+    };
+
+    for (entity, size) in balls {
+        let radius = params.radius(size.0);
+        commands.entity(entity).with_children(|ball| {
+            if settings.high_contrast {
+                ball.spawn((
+                    MaterialMesh2dBundle {
+                        mesh: outlines.meshes[size.0].clone().into(),
+                        material: outlines.material.clone(),
+                        transform: Transform::from_xyz(0.0, 0.0, -0.05),
+                        ..default()
+                    },
+                    Decoration,
+                ));
+            }
+
+            if settings.show_tiers() {
+                // the built-in font: Creepster is hard to read at a glance
+                let number = |color: Color, offset: f32, z: f32| Text2dBundle {
+                    text: Text::from_section(
+                        (size.0 + 1).to_string(),
+                        TextStyle {
+                            font_size: BADGE_FONT_SIZE,
+                            color,
+                            ..default()
+                        },
+                    ),
+                    transform: Transform::from_xyz(offset, -offset, z)
+                        .with_scale(Vec3::splat(radius / BADGE_FONT_SIZE)),
+                    ..default()
+                };
+                // a shadow so it reads on light and dark art alike
+                ball.spawn((number(Color::BLACK, radius * 0.06, 0.1), Decoration));
+                ball.spawn((number(Color::WHITE, 0.0, 0.11), Decoration));
+            }
+        });
+    }
+}
+
+#[derive(Component)]
+struct NextUpBadge;
+
+fn add_next_up_badge(next_q: Query<Entity, Added<NextUpTag>>, mut commands: Commands) {
+    for entity in next_q.iter() {
+        commands.entity(entity).with_children(|next| {
+            next.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 24.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(0.0),
+                    bottom: Val::Px(0.0),
+                    ..default()
+                })
+                .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.7)),
+                NextUpBadge,
+            ));
+        });
+    }
+}
+
+fn update_next_up_badge(
+    mut badge_q: Query<(&mut Text, &mut Visibility), With<NextUpBadge>>,
+    added_q: Query<(), Added<NextUpBadge>>,
+    next_ball_size: Res<NextNextBallSize>,
+    settings: Res<Accessibility>,
+) {
+    if !next_ball_size.is_changed() && !settings.is_changed() && added_q.is_empty() {
+        return;
+    }
+
+    for (mut text, mut visibility) in badge_q.iter_mut() {
+        text.sections[0].value = (next_ball_size.0 + 1).to_string();
+        *visibility = if settings.show_tiers() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+#[derive(Component)]
+pub struct AccessibilityButton;
+
+#[derive(Component, Clone, Copy)]
+enum Setting {
+    HighContrast,
+    ReducedMotion,
+    TierNumbers,
+    TextScale,
+}
+
+#[derive(Component)]
+struct BackButton;
+
+#[derive(Component)]
+struct AccessibilityTag;
+
+fn accessibility_button(
+    button_q: Query<&Interaction, (Changed<Interaction>, With<AccessibilityButton>)>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if button_q.iter().any(|i| *i == Interaction::Pressed) {
+        next_state.0 = Some(GameState::Accessibility);
+    }
+}
+
+fn back_button(
+    button_q: Query<&Interaction, (Changed<Interaction>, With<BackButton>)>,
+    keys: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keys.just_pressed(KeyCode::Escape) || button_q.iter().any(|i| *i == Interaction::Pressed) {
+        next_state.0 = Some(GameState::Splash);
+    }
+}
+
+fn option_buttons(
+    button_q: Query<(&Interaction, &Setting), Changed<Interaction>>,
+    mut settings: ResMut<Accessibility>,
+) {
+    for (interaction, option) in button_q.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match option {
+            Setting::HighContrast => settings.high_contrast = !settings.high_contrast,
+            Setting::ReducedMotion => settings.reduced_motion = !settings.reduced_motion,
+            Setting::TierNumbers => settings.tier_numbers = !settings.tier_numbers,
+            Setting::TextScale => {
+                let current = TEXT_SCALES
+                    .iter()
+                    .position(|&scale| scale == settings.text_scale)
+                    .unwrap_or(0);
+                settings.text_scale = TEXT_SCALES[(current + 1) % TEXT_SCALES.len()];
+            }
+        }
+        storage::save(STORAGE_KEY, &*settings);
+    }
+}
+
+/// Keeps each option's button saying what it's set to.
+fn update_labels(
+    button_q: Query<(&Setting, &Children)>,
+    mut text_q: Query<&mut Text>,
+    settings: Res<Accessibility>,
+    locale: Res<Locale>,
+) {
+    let state = |on: bool| locale.get(if on { "on" } else { "off" });
+
+    for (option, children) in button_q.iter() {
+        let value = match option {
+            Setting::HighContrast => locale.fmt(
+                "a11y-high-contrast",
+                &[("state", state(settings.high_contrast))],
+            ),
+            Setting::ReducedMotion => locale.fmt(
+                "a11y-reduced-motion",
+                &[("state", state(settings.reduced_motion))],
+            ),
+            Setting::TierNumbers => locale.fmt(
+                "a11y-tier-numbers",
+                &[("state", state(settings.tier_numbers))],
+            ),
+            Setting::TextScale => locale.fmt(
+                "a11y-text-size",
+                &[("size", format!("{:.0}", settings.text_scale * 100.0))],
+            ),
+        };
+
+        for &child in children.iter() {
+            if let Ok(mut text) = text_q.get_mut(child) {
+                if text.sections[0].value != value {
+                    text.sections[0].value = value.clone();
+                }
+            }
+        }
+    }
+}
+
+fn build_accessibility(mut commands: Commands, font: Res<CustomFont>, locale: Res<Locale>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    flex_wrap: FlexWrap::Wrap,
+                    padding: UiRect::top(Val::Px(30.0)),
+                    ..default()
+                },
+                background_color: Color::rgb_u8(52, 52, 52).into(),
+                ..default()
+            },
+            AccessibilityTag,
+            SafeArea::default(),
+        ))
+        .with_children(|root| {
+            root.spawn(TextBundle::from_section(
+                format!("{}\n\n", locale.get("a11y")),
+                TextStyle {
+                    font: font.0.clone_weak(),
+                    font_size: 40.0,
+                    color: Color::WHITE,
+                },
+            ));
+            root.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    flex_wrap: FlexWrap::Wrap,
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|button_box| {
+                let toggle = (Color::BLUE, Color::MIDNIGHT_BLUE);
+                // labels are filled in by update_labels
+                for (key, option) in [
+                    ("a11y-high-contrast", Setting::HighContrast),
+                    ("a11y-reduced-motion", Setting::ReducedMotion),
+                    ("a11y-tier-numbers", Setting::TierNumbers),
+                    ("a11y-text-size", Setting::TextScale),
+                ] {
+                    spawn_button(button_box, &font, &locale, key, toggle, 360.0, option);
+                }
+                spawn_button(
+                    button_box, &font, &locale, "back", toggle, 150.0, BackButton,
+                );
+            });
+        });
+}
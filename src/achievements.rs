@@ -0,0 +1,444 @@
+//! Achievements, unlocked from gameplay events and remembered between sessions.
+//!
+//! Only classic and chaos runs can earn them: tutorials and challenges start from boards set up to make
+//! things easy, and runs changed from the dev console don't count.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use pumpkin_game::{
+    replay::Replay,
+    sim::{BallDroppedEvent, BallsMergedEvent, GameMode, Score, BALL_ORDER},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dev::ConsoleUsed, layout::SafeArea, locale::Locale, resume, spawn_button, storage, CustomFont,
+    GameState, HintUsedEvent,
+};
+
+const STORAGE_KEY: &str = "achievements";
+
+pub struct AchievementsPlugin;
+
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(storage::load::<Unlocked>(STORAGE_KEY).unwrap_or_default())
+            .init_resource::<RunProgress>()
+            .init_resource::<Toasts>()
+            .add_systems(
+                OnEnter(GameState::Running),
+                reset_progress.before(resume::restore_run),
+            )
+            .add_systems(
+                Update,
+                track_achievements.run_if(in_state(GameState::Running)),
+            )
+            .add_systems(Update, (show_toasts, achievements_button))
+            .add_systems(OnEnter(GameState::Achievements), build_achievements)
+            .add_systems(
+                OnExit(GameState::Achievements),
+                crate::despawn_with::<AchievementsTag>,
+            )
+            .add_systems(
+                Update,
+                back_button.run_if(in_state(GameState::Achievements)),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Achievement {
+    FirstMerge,
+    Skull,
+    Pumpkin,
+    Chain5,
+    RightHanded,
+    Unassisted,
+    Score2000,
+}
+
+impl Achievement {
+    const ALL: &'static [Achievement] = &[
+        Achievement::FirstMerge,
+        Achievement::Skull,
+        Achievement::Pumpkin,
+        Achievement::Chain5,
+        Achievement::RightHanded,
+        Achievement::Unassisted,
+        Achievement::Score2000,
+    ];
+
+    /// Names this achievement's messages in the locale files.
+    fn key(self) -> &'static str {
+        match self {
+            Achievement::FirstMerge => "first_merge",
+            Achievement::Skull => "skull",
+            Achievement::Pumpkin => "pumpkin",
+            Achievement::Chain5 => "chain5",
+            Achievement::RightHanded => "right_handed",
+            Achievement::Unassisted => "unassisted",
+            Achievement::Score2000 => "score2000",
+        }
+    }
+
+    fn name(self, locale: &Locale) -> String {
+        locale.get(&format!("achievement-{}", self.key()))
+    }
+
+    fn description(self, locale: &Locale) -> String {
+        locale.get(&format!("achievement-{}-desc", self.key()))
+    }
+}
+
+/// Everything unlocked so far, in the order it happened.
+#[derive(Resource, Default, Serialize, Deserialize)]
+struct Unlocked(Vec<Achievement>);
+
+impl Unlocked {
+    /// Returns whether this is the first time.
+    fn unlock(&mut self, achievement: Achievement) -> bool {
+        if self.0.contains(&achievement) {
+            return false;
+        }
+        self.0.push(achievement);
+        storage::save(STORAGE_KEY, self);
+        true
+    }
+}
+
+/// Things the current run has done that rule achievements out. Saved with the run, so a
+/// continued run remembers them.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunProgress {
+    dropped_left: bool,
+    used_hint: bool,
+}
+
+fn reset_progress(mut progress: ResMut<RunProgress>) {
+    *progress = RunProgress::default();
+}
+
+/// Whether a run in `mode` can earn achievements.
+fn eligible(mode: GameMode, console_used: bool) -> bool {
+    matches!(mode, GameMode::Classic | GameMode::Chaos) && !console_used
+}
+
+/// What a run has earned, given the merges it just made, as their size and multiplier, and where
+/// it's got to.
+fn earned(
+    merges: impl Iterator<Item = (usize, usize)>,
+    progress: &RunProgress,
+    score: usize,
+) -> Vec<Achievement> {
+    let mut earned = Vec::new();
+
+    for (size, multiplier) in merges {
+        earned.push(Achievement::FirstMerge);
+        if size == BALL_ORDER.len() - 2 {
+            earned.push(Achievement::Skull);
+        }
+        if size == BALL_ORDER.len() - 1 {
+            earned.push(Achievement::Pumpkin);
+        }
+        if multiplier >= 5 {
+            earned.push(Achievement::Chain5);
+        }
+    }
+
+    if score >= 1000 && !progress.dropped_left {
+        earned.push(Achievement::RightHanded);
+    }
+    if score >= 1000 && !progress.used_hint {
+        earned.push(Achievement::Unassisted);
+    }
+    if score >= 2000 {
+        earned.push(Achievement::Score2000);
+    }
+    earned
+}
+
+#[allow(clippy::too_many_arguments)]
+fn track_achievements(
+    mut dropped_er: EventReader<BallDroppedEvent>,
+    mut merged_er: EventReader<BallsMergedEvent>,
+    mut hint_er: EventReader<HintUsedEvent>,
+    mut progress: ResMut<RunProgress>,
+    mut unlocked: ResMut<Unlocked>,
+    mut toasts: ResMut<Toasts>,
+    score: Res<Score>,
+    replay: Res<Replay>,
+    console: Res<ConsoleUsed>,
+) {
+    if dropped_er.iter().any(|ev| ev.position.x < 0.0) {
+        progress.dropped_left = true;
+    }
+    if hint_er.iter().next().is_some() {
+        progress.used_hint = true;
+    }
+
+    let merges = merged_er.iter().map(|ev| (ev.size, ev.multiplier));
+    if !eligible(replay.mode, console.0) {
+        merges.for_each(drop);
+        return;
+    }
+
+    for achievement in earned(merges, &progress, score.0) {
+        if unlocked.unlock(achievement) {
+            toasts.queue.push_back(achievement);
+        }
+    }
+}
+
+/// Unlocks waiting to be shown, one at a time.
+#[derive(Resource, Default)]
+struct Toasts {
+    queue: VecDeque<Achievement>,
+}
+
+#[derive(Component)]
+struct Toast(Timer);
+
+fn show_toasts(
+    mut toasts: ResMut<Toasts>,
+    mut toast_q: Query<(Entity, &mut Toast, &mut BackgroundColor, &Children)>,
+    mut text_q: Query<&mut Text>,
+    mut commands: Commands,
+    font: Res<CustomFont>,
+    locale: Res<Locale>,
+    time: Res<Time>,
+) {
+    if let Ok((entity, mut toast, mut background, children)) = toast_q.get_single_mut() {
+        toast.0.tick(time.delta());
+        // fade over the last half second
+        let alpha = (toast.0.remaining_secs() * 2.0).min(1.0);
+        background.0.set_a(0.8 * alpha);
+        for &child in children.iter() {
+            if let Ok(mut text) = text_q.get_mut(child) {
+                for section in text.sections.iter_mut() {
+                    section.style.color.set_a(alpha);
+                }
+            }
+        }
+        if toast.0.finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    let Some(achievement) = toasts.queue.pop_front() else {
+        return;
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(90.0),
+                    left: Val::Percent(10.0),
+                    width: Val::Percent(80.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0.1, 0.1, 0.1, 0.8).into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            Toast(Timer::from_seconds(3.0, TimerMode::Once)),
+        ))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_sections([
+                    TextSection {
+                        value: format!("{}\n", locale.get("achievement-unlocked")),
+                        style: TextStyle {
+                            font: font.0.clone_weak(),
+                            font_size: 20.0,
+                            color: Color::ORANGE,
+                        },
+                    },
+                    TextSection {
+                        value: achievement.name(&locale),
+                        style: TextStyle {
+                            font: font.0.clone_weak(),
+                            font_size: 30.0,
+                            color: Color::WHITE,
+                        },
+                    },
+                ])
+                .with_text_alignment(TextAlignment::Center),
+            );
+        });
+}
+
+#[derive(Component)]
+pub struct AchievementsButton;
+
+#[derive(Component)]
+struct BackButton;
+
+#[derive(Component)]
+struct AchievementsTag;
+
+fn achievements_button(
+    button_q: Query<&Interaction, (Changed<Interaction>, With<AchievementsButton>)>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if button_q.iter().any(|i| *i == Interaction::Pressed) {
+        next_state.0 = Some(GameState::Achievements);
+    }
+}
+
+fn back_button(
+    button_q: Query<&Interaction, (Changed<Interaction>, With<BackButton>)>,
+    keys: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keys.just_pressed(KeyCode::Escape) || button_q.iter().any(|i| *i == Interaction::Pressed) {
+        next_state.0 = Some(GameState::Splash);
+    }
+}
+
+fn build_achievements(
+    mut commands: Commands,
+    font: Res<CustomFont>,
+    locale: Res<Locale>,
+    unlocked: Res<Unlocked>,
+) {
+    let heading = |value: String, font_size: f32, color: Color| TextSection {
+        value,
+        style: TextStyle {
+            font: font.0.clone_weak(),
+            font_size,
+            color,
+        },
+    };
+
+    let mut sections = vec![heading(
+        format!(
+            "{}\n\n",
+            locale.fmt(
+                "achievements-heading",
+                &[
+                    ("unlocked", unlocked.0.len().to_string()),
+                    ("total", Achievement::ALL.len().to_string()),
+                ],
+            )
+        ),
+        40.0,
+        Color::WHITE,
+    )];
+    for &achievement in Achievement::ALL {
+        let name = achievement.name(&locale);
+        let (name, color) = if unlocked.0.contains(&achievement) {
+            (name, Color::ORANGE)
+        } else {
+            (
+                locale.fmt("achievement-locked", &[("name", name)]),
+                Color::GRAY,
+            )
+        };
+        sections.push(heading(format!("{}\n", name), 30.0, color));
+        sections.push(heading(
+            format!("{}\n\n", achievement.description(&locale)),
+            20.0,
+            color,
+        ));
+    }
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    flex_wrap: FlexWrap::Wrap,
+                    padding: UiRect::top(Val::Px(30.0)),
+                    ..default()
+                },
+                background_color: Color::rgb_u8(52, 52, 52).into(),
+                ..default()
+            },
+            AchievementsTag,
+            SafeArea::default(),
+        ))
+        .with_children(|root| {
+            root.spawn(
+                TextBundle::from_sections(sections).with_text_alignment(TextAlignment::Center),
+            );
+            root.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|button_box| {
+                spawn_button(
+                    button_box,
+                    &font,
+                    &locale,
+                    "back",
+                    (Color::BLUE, Color::MIDNIGHT_BLUE),
+                    150.0,
+                    BackButton,
+                );
+            });
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_earn_by_size_and_chain() {
+        let progress = RunProgress::default();
+        let skull = BALL_ORDER.len() - 2;
+        let pumpkin = BALL_ORDER.len() - 1;
+
+        assert_eq!(
+            earned([(1, 1)].into_iter(), &progress, 0),
+            [Achievement::FirstMerge]
+        );
+        let big = earned([(skull, 1), (pumpkin, 5)].into_iter(), &progress, 0);
+        for achievement in [
+            Achievement::Skull,
+            Achievement::Pumpkin,
+            Achievement::Chain5,
+        ] {
+            assert!(big.contains(&achievement), "{:?} missing", achievement);
+        }
+    }
+
+    #[test]
+    fn drops_on_the_left_and_hints_rule_out_their_achievements() {
+        let clean = earned(std::iter::empty(), &RunProgress::default(), 1000);
+        assert_eq!(clean, [Achievement::RightHanded, Achievement::Unassisted]);
+
+        let progress = RunProgress {
+            dropped_left: true,
+            used_hint: true,
+        };
+        assert!(earned(std::iter::empty(), &progress, 1500).is_empty());
+        assert_eq!(
+            earned(std::iter::empty(), &progress, 2000),
+            [Achievement::Score2000]
+        );
+    }
+
+    #[test]
+    fn only_untouched_classic_and_chaos_runs_count() {
+        assert!(eligible(GameMode::Classic, false));
+        assert!(eligible(GameMode::Chaos, false));
+        assert!(!eligible(GameMode::Tutorial, false));
+        assert!(!eligible(GameMode::Challenge, false));
+        assert!(!eligible(GameMode::Classic, true));
+        assert!(!eligible(GameMode::Chaos, true));
+    }
+}
@@ -0,0 +1,64 @@
+//! The names of every file in `assets/`. The game loads by these, and the asset pipeline builds
+//! them and lists them in [`MANIFEST`] for the service worker to cache.
+
+use crate::sim::BALL_ORDER;
+
+/// Sizes each ball image comes in, in pixels, smallest first.
+pub const QUALITIES: &[usize] = &[32, 64, 128, 256, 512];
+
+/// Ball images are packed into one atlas per quality, [`BALL_ORDER`] running left to right then
+/// top to bottom with no padding. The balls are round, so their tiles' edges are transparent
+/// anyway.
+pub const ATLAS_COLUMNS: usize = 4;
+
+pub const FONT: &str = "Creepster-Regular.ttf";
+pub const BACKGROUND: &str = "bg.png";
+pub const FOREGROUND: &str = "fg.png";
+
+pub const DROP_SOUND: &str = "drop-1.ogg";
+pub const MERGE_SOUND: &str = "pop-1.ogg";
+pub const GAME_OVER_SOUND: &str = "game-over.ogg";
+pub const MUSIC: &str = "spook.ogg";
+/// Plays along with [`MUSIC`] as the board fills up. The same length, so they stay in time.
+pub const MUSIC_TENSE: &str = "spook-tense.ogg";
+pub const STING_SOUND: &str = "sting.ogg";
+
+pub const SOUNDS: &[&str] = &[
+    DROP_SOUND,
+    MERGE_SOUND,
+    GAME_OVER_SOUND,
+    MUSIC,
+    MUSIC_TENSE,
+    STING_SOUND,
+];
+
+/// JSON list of every other asset, written by the pipeline.
+pub const MANIFEST: &str = "manifest.json";
+
+pub fn ball_atlas(quality: usize) -> String {
+    format!("balls@{}.png", quality)
+}
+
+pub fn atlas_rows() -> usize {
+    BALL_ORDER.len().div_ceil(ATLAS_COLUMNS)
+}
+
+/// Top left corner of `size`'s tile in an atlas of `quality`, in pixels.
+pub fn atlas_offset(size: usize, quality: usize) -> (usize, usize) {
+    (
+        (size % ATLAS_COLUMNS) * quality,
+        (size / ATLAS_COLUMNS) * quality,
+    )
+}
+
+/// Every asset the game needs, sorted.
+pub fn all() -> Vec<String> {
+    let mut files: Vec<String> = QUALITIES
+        .iter()
+        .map(|&q| ball_atlas(q))
+        .chain([FONT, BACKGROUND, FOREGROUND].map(String::from))
+        .chain(SOUNDS.iter().map(|s| s.to_string()))
+        .collect();
+    files.sort();
+    files
+}
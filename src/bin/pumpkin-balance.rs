@@ -0,0 +1,173 @@
+//! Sweeps the tunable rules over bot-played seeded games and prints a CSV of how each
+//! combination plays.
+//!
+//! Usage: `pumpkin-balance CONFIG.toml`. See `balance.toml` in the repo root for the format:
+//! `[base]` sets any [`SimParams`] field, and every list under `[sweep]` multiplies the number of
+//! combinations tried.
+
+use pumpkin_game::{
+    bot::{play_app, strategy_by_name, GameResult, STRATEGIES},
+    replay::headless_app,
+    sim::{RadiusEasing, SimParams, SIM_HZ},
+};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    #[serde(default = "default_games")]
+    games: u64,
+    #[serde(default)]
+    seed: u64,
+    #[serde(default = "default_strategy")]
+    strategy: String,
+    #[serde(default)]
+    base: SimParams,
+    #[serde(default)]
+    sweep: Sweep,
+}
+
+fn default_games() -> u64 {
+    20
+}
+
+fn default_strategy() -> String {
+    "greedy".into()
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct Sweep {
+    gravity: Option<Vec<f32>>,
+    linear_damping: Option<Vec<f32>>,
+    angular_damping: Option<Vec<f32>>,
+    friction: Option<Vec<f32>>,
+    restitution: Option<Vec<f32>>,
+    spawn_weights: Option<Vec<Vec<f32>>>,
+    radius_easing: Option<Vec<RadiusEasing>>,
+    combo_window: Option<Vec<f32>>,
+    combo_end_bonus: Option<Vec<usize>>,
+}
+
+/// Replaces every combination so far with one copy per value in `values`.
+fn expand<T: Clone>(
+    combos: Vec<SimParams>,
+    values: &Option<Vec<T>>,
+    set: impl Fn(&mut SimParams, T),
+) -> Vec<SimParams> {
+    let Some(values) = values else {
+        return combos;
+    };
+
+    combos
+        .into_iter()
+        .flat_map(|params| {
+            let set = &set;
+            values.iter().map(move |v| {
+                let mut params = params.clone();
+                set(&mut params, v.clone());
+                params
+            })
+        })
+        .collect()
+}
+
+fn combinations(base: &SimParams, sweep: &Sweep) -> Vec<SimParams> {
+    let mut combos = vec![base.clone()];
+    combos = expand(combos, &sweep.gravity, |p, v| p.gravity = v);
+    combos = expand(combos, &sweep.linear_damping, |p, v| p.linear_damping = v);
+    combos = expand(combos, &sweep.angular_damping, |p, v| p.angular_damping = v);
+    combos = expand(combos, &sweep.friction, |p, v| p.friction = v);
+    combos = expand(combos, &sweep.restitution, |p, v| p.restitution = v);
+    combos = expand(combos, &sweep.spawn_weights, |p, v| p.spawn_weights = v);
+    combos = expand(combos, &sweep.radius_easing, |p, v| p.radius_easing = v);
+    combos = expand(combos, &sweep.combo_window, |p, v| p.combo.window = v);
+    combos = expand(combos, &sweep.combo_end_bonus, |p, v| p.combo.end_bonus = v);
+    combos
+}
+
+fn run(config: &Config, params: &SimParams) -> Vec<GameResult> {
+    (0..config.games)
+        .map(|i| {
+            let seed = config.seed + i;
+            let mut strategy =
+                strategy_by_name(&config.strategy, seed).expect("strategy checked up front");
+
+            let mut app = headless_app();
+            app.insert_resource(params.clone());
+            play_app(app, strategy.as_mut(), seed)
+        })
+        .collect()
+}
+
+fn main() {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: pumpkin-balance CONFIG.toml");
+        std::process::exit(2);
+    };
+
+    let config: Config = match std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| toml::from_str(&s).map_err(|e| e.to_string()))
+    {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("couldn't load {}: {}", path, e);
+            std::process::exit(2);
+        }
+    };
+
+    if !STRATEGIES.contains(&config.strategy.as_str()) {
+        eprintln!(
+            "unknown strategy {}, try one of {:?}",
+            config.strategy, STRATEGIES
+        );
+        std::process::exit(2);
+    }
+
+    let combos = combinations(&config.base, &config.sweep);
+    for params in &combos {
+        if let Err(e) = params.check() {
+            eprintln!("couldn't load {}: {}", path, e);
+            std::process::exit(2);
+        }
+    }
+    eprintln!(
+        "{} combinations x {} games with {}",
+        combos.len(),
+        config.games,
+        config.strategy
+    );
+
+    println!(
+        "gravity,linear_damping,angular_damping,friction,restitution,spawn_weights,radius_easing,\
+         combo_window,combo_end_bonus,games,avg_score,avg_max_tier,max_tier,avg_drops,avg_seconds"
+    );
+
+    for params in &combos {
+        let results = run(&config, params);
+        let n = results.len().max(1) as f32;
+
+        let avg = |f: fn(&GameResult) -> f32| results.iter().map(f).sum::<f32>() / n;
+        let weights: Vec<String> = params.spawn_weights.iter().map(|w| w.to_string()).collect();
+
+        println!(
+            "{},{},{},{},{},{},{},{},{},{},{:.1},{:.2},{},{:.1},{:.1}",
+            params.gravity,
+            params.linear_damping,
+            params.angular_damping,
+            params.friction,
+            params.restitution,
+            weights.join(" "),
+            format!("{:?}", params.radius_easing).to_lowercase(),
+            params.combo.window,
+            params.combo.end_bonus,
+            results.len(),
+            avg(|r| r.score as f32),
+            avg(|r| r.max_tier as f32),
+            results.iter().map(|r| r.max_tier).max().unwrap_or(0),
+            avg(|r| r.drops as f32),
+            avg(|r| r.ticks as f32 / SIM_HZ),
+        );
+    }
+}
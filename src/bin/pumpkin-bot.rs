@@ -0,0 +1,160 @@
+//! Plays seeded games with the built-in bots and reports how they scored.
+//!
+//! Usage: `pumpkin-bot [--games N] [--seed S] [--strategy NAME]... [--level LEVEL] [--csv]`
+//!
+//! With no `--strategy` every strategy is run. Game `i` of every strategy uses seed `S + i`, so
+//! they all face the same ball sequences. `--level` plays in one of the built-in levels, by name,
+//! or a level file, instead of the classic box. `--csv` prints one row per game instead of a
+//! summary.
+
+use pumpkin_game::{
+    bot::{play_app, strategy_by_name, GameResult, STRATEGIES},
+    level::Level,
+    replay::headless_app,
+    sim::RunConfig,
+};
+
+const USAGE: &str =
+    "usage: pumpkin-bot [--games N] [--seed S] [--strategy NAME]... [--level LEVEL] [--csv]";
+
+struct Args {
+    games: u64,
+    seed: u64,
+    strategies: Vec<String>,
+    level: Level,
+    csv: bool,
+}
+
+/// A built-in level by name, or else a level file.
+fn load_level(name: &str) -> Result<Level, String> {
+    if let Some(level) = Level::built_in(name) {
+        return Ok(level);
+    }
+    let source = std::fs::read_to_string(name).map_err(|e| format!("{}: {}", name, e))?;
+    Level::from_toml(&source).map_err(|e| format!("{}: {}", name, e))
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        games: 20,
+        seed: 0,
+        strategies: Vec::new(),
+        level: Level::default(),
+        csv: false,
+    };
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--games" => args.games = value()?.parse().map_err(|e| format!("--games: {}", e))?,
+            "--seed" => args.seed = value()?.parse().map_err(|e| format!("--seed: {}", e))?,
+            "--strategy" => args.strategies.push(value()?),
+            "--level" => args.level = load_level(&value()?)?,
+            "--csv" => args.csv = true,
+            _ => return Err(format!("unknown argument {}", arg)),
+        }
+    }
+
+    if args.strategies.is_empty() {
+        args.strategies = STRATEGIES.iter().map(|s| s.to_string()).collect();
+    }
+
+    Ok(args)
+}
+
+fn percentile(sorted: &[usize], p: f32) -> usize {
+    let i = ((sorted.len() - 1) as f32 * p).round() as usize;
+    sorted[i]
+}
+
+fn summarise(name: &str, results: &[GameResult]) {
+    let mut scores: Vec<usize> = results.iter().map(|r| r.score).collect();
+    scores.sort_unstable();
+
+    let n = results.len() as f32;
+    let mean = scores.iter().sum::<usize>() as f32 / n;
+    let sd = (scores
+        .iter()
+        .map(|&s| (s as f32 - mean).powi(2))
+        .sum::<f32>()
+        / n)
+        .sqrt();
+    let drops = results.iter().map(|r| r.drops).sum::<usize>() as f32 / n;
+    let tier = results.iter().map(|r| r.max_tier).max().unwrap_or(0);
+
+    println!(
+        "{:<10} {:>5} {:>8.1} {:>7.1} {:>6} {:>6} {:>6} {:>6} {:>6} {:>7.1} {:>5}",
+        name,
+        results.len(),
+        mean,
+        sd,
+        scores[0],
+        percentile(&scores, 0.25),
+        percentile(&scores, 0.5),
+        percentile(&scores, 0.75),
+        scores[scores.len() - 1],
+        drops,
+        tier,
+    );
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if args.csv {
+        println!("strategy,seed,score,drops,ticks,max_tier");
+    } else {
+        println!(
+            "{:<10} {:>5} {:>8} {:>7} {:>6} {:>6} {:>6} {:>6} {:>6} {:>7} {:>5}",
+            "strategy",
+            "games",
+            "mean",
+            "sd",
+            "min",
+            "p25",
+            "median",
+            "p75",
+            "max",
+            "drops",
+            "tier"
+        );
+    }
+
+    for name in &args.strategies {
+        let mut results = Vec::new();
+
+        for i in 0..args.games {
+            let seed = args.seed + i;
+            let Some(mut strategy) = strategy_by_name(name, seed) else {
+                eprintln!("unknown strategy {}, try one of {:?}", name, STRATEGIES);
+                std::process::exit(2);
+            };
+
+            let mut app = headless_app();
+            app.insert_resource(RunConfig {
+                level: args.level.clone(),
+                ..Default::default()
+            });
+            let result = play_app(app, strategy.as_mut(), seed);
+            if args.csv {
+                println!(
+                    "{},{},{},{},{},{}",
+                    name, result.seed, result.score, result.drops, result.ticks, result.max_tier
+                );
+            }
+            results.push(result);
+        }
+
+        if !args.csv && !results.is_empty() {
+            summarise(name, &results);
+        }
+    }
+}
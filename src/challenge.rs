@@ -0,0 +1,200 @@
+//! Challenges: a [`Level`] with balls already on the board and a queue to deal, played for a
+//! [`Goal`]. Meeting it earns one to three stars, by how few drops it took. The ones that come
+//! with the game are under `challenges/` in the repo, in the order they're unlocked.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    level::{Level, LevelError},
+    sim::BALL_ORDER,
+};
+
+/// The challenges that come with the game, by id, in order.
+pub const CHALLENGES: &[(&str, &str)] = &[
+    ("ghost", include_str!("../challenges/ghost.toml")),
+    ("spiders", include_str!("../challenges/spiders.toml")),
+    ("skull", include_str!("../challenges/skull.toml")),
+    ("score", include_str!("../challenges/score.toml")),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Challenge {
+    pub goal: Goal,
+    /// The most drops meeting the goal can take for two stars, then for three.
+    pub stars: [usize; 2],
+    pub level: Level,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Goal {
+    /// Have a ball this big or bigger on the board.
+    Make {
+        size: usize,
+    },
+    /// Have no balls of this size left on the board.
+    Clear {
+        size: usize,
+    },
+    Score {
+        points: usize,
+    },
+}
+
+impl Goal {
+    /// Whether a board with balls of `sizes` and a run on `score` meets this.
+    pub fn met(self, mut sizes: impl Iterator<Item = usize>, score: usize) -> bool {
+        match self {
+            Goal::Make { size } => sizes.any(|s| s >= size),
+            Goal::Clear { size } => sizes.all(|s| s != size),
+            Goal::Score { points } => score >= points,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChallengeError {
+    Parse(String),
+    Level(LevelError),
+    /// Met before the first drop, or about a size there isn't.
+    BadGoal,
+    /// Out of order, or needing more drops than the queue has.
+    BadStars,
+}
+
+impl std::fmt::Display for ChallengeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChallengeError::Parse(e) => write!(f, "couldn't read challenge: {}", e),
+            ChallengeError::Level(e) => write!(f, "{}", e),
+            ChallengeError::BadGoal => write!(f, "the goal can't be played for"),
+            ChallengeError::BadStars => write!(f, "the star ratings can't be earned"),
+        }
+    }
+}
+
+impl std::error::Error for ChallengeError {}
+
+impl Challenge {
+    pub fn from_toml(source: &str) -> Result<Self, ChallengeError> {
+        let challenge: Challenge =
+            toml::from_str(source).map_err(|e| ChallengeError::Parse(e.to_string()))?;
+        challenge.check()?;
+        Ok(challenge)
+    }
+
+    /// Every one of [`CHALLENGES`], with its id.
+    pub fn built_in() -> Vec<(&'static str, Challenge)> {
+        CHALLENGES
+            .iter()
+            .filter_map(|&(id, source)| Some((id, Challenge::from_toml(source).ok()?)))
+            .collect()
+    }
+
+    pub fn check(&self) -> Result<(), ChallengeError> {
+        self.level.check().map_err(ChallengeError::Level)?;
+
+        let size_ok = match self.goal {
+            Goal::Make { size } | Goal::Clear { size } => size < BALL_ORDER.len(),
+            Goal::Score { points } => points > 0,
+        };
+        let placed = self.level.balls.iter().map(|ball| ball.size);
+        if !size_ok || self.goal.met(placed, 0) {
+            return Err(ChallengeError::BadGoal);
+        }
+
+        let [two, three] = self.stars;
+        let in_reach = self.level.balls_left(0).is_none_or(|balls| two <= balls);
+        if three == 0 || three > two || !in_reach {
+            return Err(ChallengeError::BadStars);
+        }
+        Ok(())
+    }
+
+    /// Stars for meeting the goal in `drops`.
+    pub fn stars(&self, drops: usize) -> u8 {
+        let [two, three] = self.stars;
+        1 + u8::from(drops <= two) + u8::from(drops <= three)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::prelude::*;
+
+    use crate::{
+        replay::{headless_app, step, Replay},
+        sim::{
+            reset_run, BallSize, DropInput, NextBallSize, NextBallTimer, RunConfig, RunOver,
+            SimTick,
+        },
+    };
+
+    #[test]
+    fn challenges_load() {
+        for (id, source) in CHALLENGES {
+            assert_eq!(
+                Challenge::from_toml(source).map(|_| ()),
+                Ok(()),
+                "{} didn't load",
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn fewer_drops_earn_more_stars() {
+        let challenge = Challenge {
+            goal: Goal::Score { points: 10 },
+            stars: [8, 4],
+            level: Level::default(),
+        };
+        assert_eq!(challenge.stars(3), 3);
+        assert_eq!(challenge.stars(4), 3);
+        assert_eq!(challenge.stars(5), 2);
+        assert_eq!(challenge.stars(9), 1);
+    }
+
+    #[test]
+    fn goals_met_before_playing_are_refused() {
+        let source = "stars = [2, 1]\n\
+                      goal = { kind = \"clear\", size = 1 }\n\
+                      [level]\n\
+                      queue = [0, 0]";
+        assert_eq!(Challenge::from_toml(source), Err(ChallengeError::BadGoal));
+    }
+
+    #[test]
+    fn the_board_is_set_and_the_queue_runs_out() {
+        let (_, challenge) = Challenge::built_in().swap_remove(0);
+        let level = challenge.level;
+
+        let mut app = headless_app();
+        app.insert_resource(RunConfig {
+            level: level.clone(),
+            ..default()
+        });
+        reset_run(&mut app.world);
+
+        let placed = app.world.query::<&BallSize>().iter(&app.world).count();
+        assert_eq!(placed, level.balls.len());
+
+        let mut dealt = Vec::new();
+        while !app.world.resource::<RunOver>().0 && app.world.resource::<SimTick>().0 < 60 * 30 {
+            if app.world.resource::<NextBallTimer>().0.finished() {
+                if dealt.len() < level.queue.len() {
+                    dealt.push(app.world.resource::<NextBallSize>().0);
+                }
+                app.world.resource_mut::<DropInput>().0 = Some((dealt.len() % 3) as f32 - 1.0);
+            }
+            step(&mut app);
+        }
+
+        assert_eq!(dealt, level.queue);
+        assert_eq!(
+            app.world.resource::<Replay>().drops.len(),
+            level.queue.len()
+        );
+    }
+}
@@ -0,0 +1,434 @@
+//! The challenge screen and challenge runs. Each challenge is unlocked by earning a star on the one
+//! before it; picking one starts a [`GameMode::Challenge`] run on its board, which ends as soon as
+//! the goal is met, or fails once the queue is empty and the board has come to rest. The best
+//! stars earned on each are remembered between sessions.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+use pumpkin_game::{
+    challenge::{Challenge, Goal},
+    level::Level,
+    replay::Replay,
+    sim::{end_run, reset_run, BallSize, GameMode, RunConfig, RunOver, Score, SimEntity, SimTick},
+};
+
+use crate::{
+    layout::SafeArea, locale::Locale, resume, spawn_button, storage, tutorial, CustomFont,
+    GameState, RunningTag,
+};
+
+const STORAGE_KEY: &str = "challenges";
+
+/// Balls slower than this count as at rest.
+const SETTLED_SPEED: f32 = 0.1;
+/// How long the board has to be at rest, with nothing left to drop, to fail.
+const SETTLE_SECS: f32 = 1.0;
+
+pub struct ChallengeUiPlugin;
+
+impl Plugin for ChallengeUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Challenges {
+            list: Challenge::built_in(),
+            best: storage::load(STORAGE_KEY).unwrap_or_default(),
+            ..default()
+        })
+        .add_systems(Update, challenges_button)
+        .add_systems(OnEnter(GameState::Challenges), build_challenges)
+        .add_systems(
+            OnExit(GameState::Challenges),
+            crate::despawn_with::<ChallengesTag>,
+        )
+        .add_systems(
+            Update,
+            (pick_button, back_button).run_if(in_state(GameState::Challenges)),
+        )
+        .add_systems(OnEnter(GameState::Splash), leave_challenges)
+        .add_systems(
+            OnEnter(GameState::Running),
+            pick_challenge.after(tutorial::pick_mode).before(reset_run),
+        )
+        .add_systems(
+            OnEnter(GameState::Running),
+            build_goal.after(resume::restore_run),
+        )
+        .add_systems(
+            Update,
+            (judge_challenge, update_goal)
+                .chain()
+                .run_if(in_state(GameState::Running)),
+        );
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Challenges {
+    list: Vec<(&'static str, Challenge)>,
+    /// Most stars earned on each, by id.
+    best: HashMap<String, u8>,
+    /// Picked on the challenge screen, for the next run to play.
+    picked: Option<usize>,
+    /// The one being played, kept so playing again has another go at it.
+    current: Option<usize>,
+    /// Stars the run earned, once it's been judged.
+    stars: Option<u8>,
+    /// Whether the run config holds a challenge's level, to be put back for a normal run.
+    level_set: bool,
+    /// Seconds the board has been at rest with nothing left to drop.
+    settled: f32,
+}
+
+impl Challenges {
+    /// Stars from the challenge run that just ended, 0 if it was failed, or `None` if it wasn't
+    /// a challenge.
+    pub fn result(&self) -> Option<u8> {
+        self.current.map(|_| self.stars.unwrap_or(0))
+    }
+
+    fn best_stars(&self, index: usize) -> u8 {
+        self.best.get(self.list[index].0).copied().unwrap_or(0)
+    }
+
+    fn unlocked(&self, index: usize) -> bool {
+        index == 0 || self.best_stars(index - 1) > 0
+    }
+}
+
+#[derive(Component)]
+pub struct ChallengesButton;
+
+#[derive(Component)]
+struct ChallengesTag;
+
+#[derive(Component)]
+struct PickButton(usize);
+
+#[derive(Component)]
+struct BackButton;
+
+#[derive(Component)]
+struct GoalText;
+
+fn describe(goal: Goal, locale: &Locale) -> String {
+    match goal {
+        Goal::Make { size } => locale.fmt("goal-make", &[("ball", locale.ball(size))]),
+        Goal::Clear { size } => locale.fmt("goal-clear", &[("ball", locale.ball(size))]),
+        Goal::Score { points } => locale.fmt("goal-score", &[("points", points.to_string())]),
+    }
+}
+
+/// A row of three stars, the first `earned` lit.
+pub fn spawn_stars(parent: &mut ChildBuilder, earned: u8, size: f32) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|row| {
+            for star in 0..3 {
+                let color = if star < earned {
+                    Color::ORANGE
+                } else {
+                    Color::GRAY
+                };
+                row.spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(size),
+                        height: Val::Px(size),
+                        margin: UiRect::all(Val::Px(size / 4.0)),
+                        ..default()
+                    },
+                    background_color: color.into(),
+                    ..default()
+                });
+            }
+        });
+}
+
+fn challenges_button(
+    button_q: Query<&Interaction, (Changed<Interaction>, With<ChallengesButton>)>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if button_q.iter().any(|i| *i == Interaction::Pressed) {
+        next_state.0 = Some(GameState::Challenges);
+    }
+}
+
+fn pick_button(
+    button_q: Query<(&Interaction, &PickButton), Changed<Interaction>>,
+    mut challenges: ResMut<Challenges>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, pick) in button_q.iter() {
+        if *interaction == Interaction::Pressed {
+            challenges.picked = Some(pick.0);
+            next_state.0 = Some(GameState::Running);
+        }
+    }
+}
+
+fn back_button(
+    button_q: Query<&Interaction, (Changed<Interaction>, With<BackButton>)>,
+    keys: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keys.just_pressed(KeyCode::Escape) || button_q.iter().any(|i| *i == Interaction::Pressed) {
+        next_state.0 = Some(GameState::Splash);
+    }
+}
+
+fn build_challenges(
+    mut commands: Commands,
+    challenges: Res<Challenges>,
+    font: Res<CustomFont>,
+    locale: Res<Locale>,
+) {
+    let text = |value: String, font_size: f32| {
+        TextBundle::from_section(
+            value,
+            TextStyle {
+                font: font.0.clone_weak(),
+                font_size,
+                color: Color::WHITE,
+            },
+        )
+        .with_text_alignment(TextAlignment::Center)
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    flex_wrap: FlexWrap::Wrap,
+                    padding: UiRect::top(Val::Px(30.0)),
+                    ..default()
+                },
+                background_color: Color::rgb_u8(52, 52, 52).into(),
+                ..default()
+            },
+            ChallengesTag,
+            SafeArea::default(),
+        ))
+        .with_children(|root| {
+            root.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    margin: UiRect::bottom(Val::Px(20.0)),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|heading| {
+                heading.spawn(text(locale.get("challenges"), 40.0));
+            });
+
+            for (index, (_, challenge)) in challenges.list.iter().enumerate() {
+                let unlocked = challenges.unlocked(index);
+                let (background, border) = if unlocked {
+                    (Color::BLUE, Color::MIDNIGHT_BLUE)
+                } else {
+                    (Color::DARK_GRAY, Color::GRAY)
+                };
+
+                let mut card = root.spawn(ButtonBundle {
+                    background_color: background.into(),
+                    border_color: border.into(),
+                    style: Style {
+                        width: Val::Px(160.0),
+                        padding: UiRect::all(Val::Px(10.0)),
+                        border: UiRect::all(Val::Px(5.0)),
+                        margin: UiRect::all(Val::Px(10.0)),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    ..default()
+                });
+                if unlocked {
+                    card.insert(PickButton(index));
+                }
+                card.with_children(|card| {
+                    card.spawn(text((index + 1).to_string(), 30.0));
+                    card.spawn(text(describe(challenge.goal, &locale), 20.0));
+                    spawn_stars(card, challenges.best_stars(index), 16.0);
+                });
+            }
+
+            root.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|button_box| {
+                spawn_button(
+                    button_box,
+                    &font,
+                    &locale,
+                    "back",
+                    (Color::BLUE, Color::MIDNIGHT_BLUE),
+                    150.0,
+                    BackButton,
+                );
+            });
+        });
+}
+
+fn leave_challenges(mut challenges: ResMut<Challenges>) {
+    challenges.current = None;
+}
+
+/// Sets the run that's starting up for the challenge picked or being played again. Any other
+/// run gets the classic box back.
+fn pick_challenge(mut challenges: ResMut<Challenges>, mut config: ResMut<RunConfig>) {
+    if let Some(index) = challenges.picked.take() {
+        challenges.current = Some(index);
+    }
+    challenges.stars = None;
+    challenges.settled = 0.0;
+
+    match challenges.current {
+        Some(index) => {
+            config.mode = GameMode::Challenge;
+            config.level = challenges.list[index].1.level.clone();
+            challenges.level_set = true;
+        }
+        None => {
+            if std::mem::take(&mut challenges.level_set) {
+                config.level = Level::default();
+            }
+        }
+    }
+}
+
+fn build_goal(
+    mut commands: Commands,
+    challenges: Res<Challenges>,
+    font: Res<CustomFont>,
+    locale: Res<Locale>,
+) {
+    let Some(index) = challenges.current else {
+        return;
+    };
+
+    let style = TextStyle {
+        font: font.0.clone_weak(),
+        font_size: 24.0,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(44.0),
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            RunningTag,
+            SafeArea::default(),
+        ))
+        .with_children(|strip| {
+            strip.spawn((
+                TextBundle::from_sections([
+                    TextSection::new(
+                        describe(challenges.list[index].1.goal, &locale),
+                        style.clone(),
+                    ),
+                    TextSection::new("", style),
+                ]),
+                GoalText,
+            ));
+        });
+}
+
+fn update_goal(
+    mut text_q: Query<&mut Text, With<GoalText>>,
+    replay: Res<Replay>,
+    level: Res<Level>,
+    locale: Res<Locale>,
+) {
+    let Ok(mut text) = text_q.get_single_mut() else {
+        return;
+    };
+    if !replay.is_changed() && !text.is_added() {
+        return;
+    }
+
+    text.sections[1].value = match level.balls_left(replay.drops.len()) {
+        Some(left) => format!(
+            "  {}",
+            locale.fmt("balls-left", &[("balls", left.to_string())])
+        ),
+        None => String::new(),
+    };
+}
+
+/// Ends a challenge run as soon as its goal is met, or once the last ball has been dropped and
+/// the board has come to rest without meeting it.
+#[allow(clippy::too_many_arguments)]
+fn judge_challenge(
+    ball_q: Query<(&BallSize, &LinearVelocity), With<SimEntity>>,
+    mut challenges: ResMut<Challenges>,
+    mut over: ResMut<RunOver>,
+    mut replay: ResMut<Replay>,
+    mut next_state: ResMut<NextState<GameState>>,
+    tick: Res<SimTick>,
+    score: Res<Score>,
+    level: Res<Level>,
+    time: Res<Time>,
+) {
+    let Some(index) = challenges.current else {
+        return;
+    };
+    if over.0 {
+        return;
+    }
+
+    let drops = replay.drops.len();
+    let (id, challenge) = &challenges.list[index];
+    let (id, goal, earned) = (*id, challenge.goal, challenge.stars(drops));
+
+    let stars = if goal.met(ball_q.iter().map(|(size, _)| size.0), score.0) {
+        earned
+    } else if level.balls_left(drops) == Some(0) {
+        // give the last ball time to land and anything it sets off time to happen
+        let still = ball_q
+            .iter()
+            .all(|(_, velocity)| velocity.0.length() < SETTLED_SPEED);
+        challenges.settled = if still {
+            challenges.settled + time.delta_seconds()
+        } else {
+            0.0
+        };
+        if challenges.settled < SETTLE_SECS {
+            return;
+        }
+        0
+    } else {
+        return;
+    };
+
+    challenges.stars = Some(stars);
+    if stars > challenges.best_stars(index) {
+        challenges.best.insert(id.to_string(), stars);
+        storage::save(STORAGE_KEY, &challenges.best);
+    }
+    end_run(&mut over, &mut replay, &tick);
+    next_state.0 = Some(GameState::GameOver);
+}
@@ -0,0 +1,267 @@
+//! Chaos runs: every so often a [`Modifier`] takes hold of gravity for a while. It can swing
+//! slowly from side to side, drop to moon strength, flip upside down for a moment, or hand the box
+//! to the player to tilt. Which modifier comes next is drawn from the run's seed, and the player's
+//! tilting is recorded in the replay, so chaos runs play back like any other.
+
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    replay::{Replay, ReplayTilt},
+    sim::{GameMode, SimParams, SimTick, SIM_DT},
+};
+
+/// Calm before the first modifier, in seconds.
+pub const FIRST_CALM_SECS: f32 = 8.0;
+/// Calm between one modifier ending and the next starting, in seconds.
+pub const CALM_SECS: f32 = 6.0;
+/// Furthest gravity swings to either side in a swirl, in degrees.
+pub const SWIRL_ANGLE: f32 = 25.0;
+/// Gravity on the moon, as a share of the usual.
+pub const MOON_GRAVITY: f32 = 0.3;
+/// Gravity while flipped, as a share of the usual. Weak and short, so the pile lifts rather than
+/// flies.
+pub const FLIP_GRAVITY: f32 = 0.5;
+/// How long after a flip balls over the death line are still let off, while they fall back.
+pub const FLIP_GRACE_SECS: f32 = 2.0;
+/// Furthest the player can tilt the box, in degrees, right side down for positive.
+pub const MAX_TILT: f32 = 20.0;
+/// Degrees a second the box turns towards the tilt asked for.
+pub const TILT_SPEED: f32 = 40.0;
+/// Steps tilt input is rounded to each way, so a shaky hand isn't recorded every tick.
+pub const TILT_STEPS: f32 = 8.0;
+/// Mixed into the run's seed, so modifiers aren't drawn in step with the ball queue, which starts
+/// from the same seed.
+const CHAOS_STREAM: u64 = 0x6368_616f_735f_7267;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Modifier {
+    /// Gravity swings to one side and the other and back.
+    Swirl,
+    Moon,
+    Flip,
+    /// The player tilts the box, with [`TiltInput`].
+    Tilt,
+}
+
+impl Modifier {
+    pub const ALL: &'static [Modifier] = &[
+        Modifier::Swirl,
+        Modifier::Moon,
+        Modifier::Flip,
+        Modifier::Tilt,
+    ];
+
+    /// How long it lasts, in seconds.
+    pub fn secs(self) -> f32 {
+        match self {
+            Modifier::Swirl => 10.0,
+            Modifier::Moon => 8.0,
+            Modifier::Flip => 0.6,
+            Modifier::Tilt => 10.0,
+        }
+    }
+
+    /// Names this modifier's messages in the locale files.
+    pub fn key(self) -> &'static str {
+        match self {
+            Modifier::Swirl => "swirl",
+            Modifier::Moon => "moon",
+            Modifier::Flip => "flip",
+            Modifier::Tilt => "tilt",
+        }
+    }
+}
+
+/// Where a chaos run is up to. Reset with every run, and left alone outside chaos runs.
+#[derive(Resource, Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Chaos {
+    /// State of the RNG modifiers are drawn from, kept as its seed so it can be saved.
+    rng: u64,
+    /// The modifier in effect and seconds since it started.
+    pub active: Option<(Modifier, f32)>,
+    /// Seconds until the next modifier starts, while there's none.
+    calm: f32,
+    /// Seconds left of letting balls over the death line off, after a flip.
+    grace: f32,
+    /// The tilt asked for, rounded to [`TILT_STEPS`], while a tilt is on.
+    input: f32,
+    /// Degrees the box is tilted, right side down for positive. Follows the tilt asked for while
+    /// a tilt is on, and goes back level after.
+    pub tilt: f32,
+}
+
+impl Chaos {
+    pub fn new(seed: u64) -> Self {
+        Chaos {
+            rng: fastrand::Rng::with_seed(seed ^ CHAOS_STREAM).u64(..),
+            calm: FIRST_CALM_SECS,
+            ..default()
+        }
+    }
+
+    fn draw(&mut self) -> Modifier {
+        let mut rng = fastrand::Rng::with_seed(self.rng);
+        let modifier = Modifier::ALL[rng.usize(..Modifier::ALL.len())];
+        self.rng = rng.get_seed();
+        modifier
+    }
+
+    /// Gravity as a share of the usual, pointing whichever way it does right now.
+    pub fn gravity(&self) -> Vec2 {
+        let (swing, strength) = match self.active {
+            Some((Modifier::Swirl, t)) => {
+                ((t / Modifier::Swirl.secs() * TAU).sin() * SWIRL_ANGLE, 1.0)
+            }
+            Some((Modifier::Moon, _)) => (0.0, MOON_GRAVITY),
+            Some((Modifier::Flip, _)) => (180.0, FLIP_GRAVITY),
+            _ => (0.0, 1.0),
+        };
+        // to the balls, tipping the box right side down is gravity swinging round anticlockwise
+        Vec2::from_angle((swing + self.tilt).to_radians()).rotate(Vec2::NEG_Y) * strength
+    }
+
+    /// Whether balls over the death line are let off for now, as a flip may have put them there.
+    pub fn excuses_overtop(&self) -> bool {
+        matches!(self.active, Some((Modifier::Flip, _))) || self.grace > 0.0
+    }
+}
+
+/// Set by whatever is playing to tilt the box while a tilt is on, from -1 for all the way left
+/// side down to 1 for all the way right.
+#[derive(Resource, Default)]
+pub struct TiltInput(pub f32);
+
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub enum ChaosEvent {
+    Started(Modifier),
+    Ended(Modifier),
+}
+
+pub fn chaos_run(replay: Res<Replay>) -> bool {
+    replay.mode == GameMode::Chaos
+}
+
+/// Moves the current modifier along, starting and ending them, and points gravity to match.
+pub fn run_chaos(
+    mut chaos: ResMut<Chaos>,
+    mut gravity: ResMut<Gravity>,
+    mut replay: ResMut<Replay>,
+    input: Res<TiltInput>,
+    tick: Res<SimTick>,
+    params: Res<SimParams>,
+    mut chaos_ew: EventWriter<ChaosEvent>,
+) {
+    chaos.grace = (chaos.grace - SIM_DT).max(0.0);
+
+    match chaos.active {
+        Some((modifier, t)) if t + SIM_DT >= modifier.secs() => {
+            chaos.active = None;
+            chaos.calm = CALM_SECS;
+            if modifier == Modifier::Flip {
+                chaos.grace = FLIP_GRACE_SECS;
+            }
+            chaos_ew.send(ChaosEvent::Ended(modifier));
+        }
+        Some((modifier, t)) => chaos.active = Some((modifier, t + SIM_DT)),
+        None => {
+            chaos.calm -= SIM_DT;
+            if chaos.calm <= 0.0 {
+                let modifier = chaos.draw();
+                chaos.active = Some((modifier, 0.0));
+                chaos_ew.send(ChaosEvent::Started(modifier));
+            }
+        }
+    }
+
+    let target = match chaos.active {
+        Some((Modifier::Tilt, t)) => {
+            let wanted = (input.0.clamp(-1.0, 1.0) * TILT_STEPS).round() / TILT_STEPS;
+            // always recorded as a tilt starts, so playback doesn't carry over the last one's
+            if t == 0.0 || wanted != chaos.input {
+                chaos.input = wanted;
+                replay.tilts.push(ReplayTilt {
+                    tick: tick.0,
+                    tilt: wanted,
+                });
+            }
+            chaos.input * MAX_TILT
+        }
+        _ => 0.0,
+    };
+    let step = TILT_SPEED * SIM_DT;
+    chaos.tilt += (target - chaos.tilt).clamp(-step, step);
+
+    gravity.0 = chaos.gravity() * params.gravity;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        replay::{headless_app, simulate, step, ReplayOutcome},
+        sim::{reset_run, DropInput, NextBallTimer, RunConfig, RunOver, Score},
+    };
+
+    #[test]
+    fn calm_gravity_is_the_usual() {
+        assert_eq!(Chaos::new(0).gravity(), Vec2::NEG_Y);
+    }
+
+    #[test]
+    fn neighbouring_seeds_draw_their_own_modifiers() {
+        let draws = |seed| {
+            let mut chaos = Chaos::new(seed);
+            (0..16).map(|_| chaos.draw()).collect::<Vec<_>>()
+        };
+        let (first, second) = (draws(7), draws(8));
+        assert_ne!(first, second);
+        // the next seed along isn't the same run of modifiers a step behind
+        assert_ne!(first[1..], second[..15]);
+    }
+
+    #[test]
+    fn chaos_runs_with_tilting_replay() {
+        let mut app = headless_app();
+        app.insert_resource(RunConfig {
+            seed: 5,
+            mode: GameMode::Chaos,
+            ..default()
+        });
+        reset_run(&mut app.world);
+
+        let mut dropped = 0;
+        let mut modifiers = 0;
+        while !app.world.resource::<RunOver>().0 && app.world.resource::<SimTick>().0 < 60 * 60 {
+            if app.world.resource::<NextBallTimer>().0.finished() {
+                app.world.resource_mut::<DropInput>().0 = Some((dropped % 7) as f32 * 0.4 - 1.2);
+                dropped += 1;
+            }
+            // lean one way then the other, a few seconds at a time
+            let tick = app.world.resource::<SimTick>().0;
+            app.world.resource_mut::<TiltInput>().0 = if (tick / 180).is_multiple_of(2) {
+                0.7
+            } else {
+                -1.0
+            };
+            step(&mut app);
+            modifiers += app.world.resource::<Events<ChaosEvent>>().len();
+        }
+        assert!(modifiers > 0, "no modifiers came up");
+
+        let outcome = ReplayOutcome {
+            score: app.world.resource::<Score>().0,
+            ticks: app.world.resource::<SimTick>().0,
+            game_over: app.world.resource::<RunOver>().0,
+        };
+        let mut replay = app.world.resource::<Replay>().clone();
+        if !outcome.game_over {
+            replay.end_tick = outcome.ticks;
+        }
+        assert_eq!(simulate(&replay), Ok(outcome));
+    }
+}
@@ -0,0 +1,270 @@
+//! Chaos runs, from the client's side: the menu button that starts one, tilting the box with the
+//! arrow keys or by tipping the phone, turning the view to follow the box, and announcing each
+//! modifier as it comes and goes.
+
+use bevy::prelude::*;
+use pumpkin_game::{
+    chaos::{Chaos, ChaosEvent, TiltInput},
+    replay::Replay,
+    sim::{reset_run, GameMode, RunConfig},
+};
+
+use crate::{
+    accessibility::Accessibility, locale::Locale, resume, tutorial, CustomFont, GameState,
+    RunningTag,
+};
+
+/// Degrees the phone has to be tipped to tilt the box all the way.
+const FULL_TIP: f32 = 30.0;
+/// How long the all-clear stays up once a modifier ends.
+const ANNOUNCE_SECS: f32 = 2.0;
+
+pub struct ChaosUiPlugin;
+
+impl Plugin for ChaosUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChaosRuns>()
+            .add_systems(Startup, listen_for_orientation)
+            .add_systems(Update, (chaos_button, turn_view))
+            .add_systems(OnEnter(GameState::Splash), leave_chaos)
+            .add_systems(
+                OnEnter(GameState::Running),
+                pick_chaos.after(tutorial::pick_mode).before(reset_run),
+            )
+            .add_systems(
+                OnEnter(GameState::Running),
+                build_announcement.after(resume::restore_run),
+            )
+            .add_systems(
+                Update,
+                (read_tilt, announce_modifiers, fade_announcement)
+                    .run_if(in_state(GameState::Running)),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+struct ChaosRuns {
+    /// Asked for from the menu, for the next run.
+    requested: bool,
+    /// Kept so playing again stays chaotic.
+    playing: bool,
+}
+
+#[derive(Component)]
+pub struct ChaosButton;
+
+/// Tells the player what gravity is up to. Fades out once things are back to normal.
+#[derive(Component)]
+struct Announcement {
+    fade: Option<Timer>,
+}
+
+fn chaos_button(
+    button_q: Query<&Interaction, (Changed<Interaction>, With<ChaosButton>)>,
+    mut runs: ResMut<ChaosRuns>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if button_q.iter().any(|i| *i == Interaction::Pressed) {
+        runs.requested = true;
+        next_state.0 = Some(GameState::Running);
+    }
+}
+
+fn leave_chaos(mut runs: ResMut<ChaosRuns>) {
+    runs.playing = false;
+}
+
+fn pick_chaos(mut runs: ResMut<ChaosRuns>, mut config: ResMut<RunConfig>) {
+    runs.playing |= std::mem::take(&mut runs.requested);
+    if runs.playing {
+        config.mode = GameMode::Chaos;
+    }
+}
+
+fn build_announcement(
+    mut commands: Commands,
+    replay: Res<Replay>,
+    chaos: Res<Chaos>,
+    font: Res<CustomFont>,
+    locale: Res<Locale>,
+) {
+    if replay.mode != GameMode::Chaos {
+        return;
+    }
+
+    // a continued run may be partway through one
+    let value = chaos.active.map_or(String::new(), |(modifier, _)| {
+        locale.get(&format!("modifier-{}", modifier.key()))
+    });
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Percent(25.0),
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            RunningTag,
+        ))
+        .with_children(|strip| {
+            strip.spawn((
+                TextBundle::from_section(
+                    value,
+                    TextStyle {
+                        font: font.0.clone_weak(),
+                        font_size: 30.0,
+                        color: Color::ORANGE,
+                    },
+                )
+                .with_text_alignment(TextAlignment::Center)
+                .with_style(Style {
+                    max_width: Val::Px(360.0),
+                    ..default()
+                }),
+                Announcement { fade: None },
+            ));
+        });
+}
+
+fn announce_modifiers(
+    mut chaos_er: EventReader<ChaosEvent>,
+    mut announcement_q: Query<(&mut Text, &mut Announcement)>,
+    locale: Res<Locale>,
+) {
+    let Ok((mut text, mut announcement)) = announcement_q.get_single_mut() else {
+        chaos_er.clear();
+        return;
+    };
+
+    for ev in chaos_er.iter() {
+        let section = &mut text.sections[0];
+        match *ev {
+            ChaosEvent::Started(modifier) => {
+                section.value = locale.get(&format!("modifier-{}", modifier.key()));
+                section.style.color = Color::ORANGE;
+                announcement.fade = None;
+            }
+            ChaosEvent::Ended(_) => {
+                section.value = locale.get("modifier-over");
+                section.style.color = Color::WHITE;
+                announcement.fade = Some(Timer::from_seconds(ANNOUNCE_SECS, TimerMode::Once));
+            }
+        }
+    }
+}
+
+fn fade_announcement(mut announcement_q: Query<(&mut Text, &mut Announcement)>, time: Res<Time>) {
+    for (mut text, mut announcement) in announcement_q.iter_mut() {
+        let Some(fade) = announcement.fade.as_mut() else {
+            continue;
+        };
+        fade.tick(time.delta());
+        let left = fade.percent_left();
+        let section = &mut text.sections[0];
+        section.style.color.set_a(left);
+        if fade.finished() {
+            section.value.clear();
+            announcement.fade = None;
+        }
+    }
+}
+
+fn read_tilt(mut tilt: ResMut<TiltInput>, keys: Res<Input<KeyCode>>) {
+    let mut keyed = 0.0;
+    if keys.pressed(KeyCode::Left) {
+        keyed -= 1.0;
+    }
+    if keys.pressed(KeyCode::Right) {
+        keyed += 1.0;
+    }
+
+    // held keys win over the phone
+    tilt.0 = if keyed != 0.0 {
+        keyed
+    } else {
+        device_tilt().map_or(0.0, |tip| (tip / FULL_TIP).clamp(-1.0, 1.0))
+    };
+}
+
+/// Turns the camera with the box, so the box looks tipped rather than gravity looking bent.
+fn turn_view(
+    mut camera_q: Query<&mut Transform, With<Camera2d>>,
+    chaos: Res<Chaos>,
+    replay: Res<Replay>,
+    state: Res<State<GameState>>,
+    settings: Res<Accessibility>,
+) {
+    let tilted = *state.get() == GameState::Running
+        && replay.mode == GameMode::Chaos
+        && !settings.reduced_motion;
+    let tilt = if tilted { chaos.tilt } else { 0.0 };
+    // the camera turning anticlockwise shows the box turned clockwise, right side down
+    let rotation = Quat::from_rotation_z(tilt.to_radians());
+
+    for mut transform in camera_q.iter_mut() {
+        if transform.rotation != rotation {
+            transform.rotation = rotation;
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod orientation {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use wasm_bindgen::{closure::Closure, JsCast};
+
+    /// Never a tip the browser reports, as it isn't a number.
+    const UNKNOWN: u32 = u32::MAX;
+
+    /// The last left-right tip reported, as bits of an `f32`.
+    static TIP: AtomicU32 = AtomicU32::new(UNKNOWN);
+
+    pub fn listen() {
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+        let on_orientation = Closure::<dyn FnMut(web_sys::DeviceOrientationEvent)>::new(
+            |ev: web_sys::DeviceOrientationEvent| {
+                if let Some(gamma) = ev.gamma() {
+                    TIP.store((gamma as f32).to_bits(), Ordering::Relaxed);
+                }
+            },
+        );
+        let _ = window.add_event_listener_with_callback(
+            "deviceorientation",
+            on_orientation.as_ref().unchecked_ref(),
+        );
+        // listens for as long as the page is open
+        on_orientation.forget();
+    }
+
+    pub fn tip() -> Option<f32> {
+        let bits = TIP.load(Ordering::Relaxed);
+        (bits != UNKNOWN).then(|| f32::from_bits(bits))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn listen_for_orientation() {
+    orientation::listen();
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn listen_for_orientation() {}
+
+/// Degrees the phone is tipped left to right, right side down for positive, on browsers that say.
+#[cfg(target_arch = "wasm32")]
+fn device_tilt() -> Option<f32> {
+    orientation::tip()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn device_tilt() -> Option<f32> {
+    None
+}
@@ -0,0 +1,205 @@
+//! The combo in progress, as a meter in the HUD that runs down with its window, and a popup for
+//! what it paid when it ends. The rules themselves are the sim's [`ComboRules`].
+//!
+//! [`ComboRules`]: pumpkin_game::sim::ComboRules
+
+use bevy::prelude::*;
+use pumpkin_game::sim::{ComboEndedEvent, ComboTimer, Multiplier, SimParams};
+
+use crate::{accessibility::Accessibility, locale::Locale, CustomFont, GameState, RunningTag};
+
+/// Merge sounds go up a semitone for each merge in a combo, up to this many.
+const MAX_PITCH_STEPS: usize = 12;
+const POPUP_SECS: f32 = 1.2;
+const POPUP_TOP: f32 = 120.0;
+/// How far a bonus popup floats up over its life, in pixels.
+const POPUP_RISE: f32 = 40.0;
+
+pub struct ComboPlugin;
+
+impl Plugin for ComboPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (update_meter, show_bonus, float_bonus).run_if(in_state(GameState::Running)),
+        );
+    }
+}
+
+/// How much faster a merge sound plays as the `multiplier`th merge of a combo.
+pub fn pitch(multiplier: usize) -> f32 {
+    let steps = multiplier.saturating_sub(1).min(MAX_PITCH_STEPS);
+    2f32.powf(steps as f32 / 12.0)
+}
+
+#[derive(Component)]
+struct ComboMeter;
+
+#[derive(Component)]
+struct ComboText;
+
+#[derive(Component)]
+struct ComboFill;
+
+#[derive(Component)]
+struct BonusPopup(Timer);
+
+/// Adds the meter under `parent`. It stays hidden, but keeps its space, until a combo starts.
+pub fn spawn_meter(parent: &mut ChildBuilder, font: &CustomFont) {
+    parent
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Px(90.0),
+                    margin: UiRect::all(Val::Px(10.0)),
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            ComboMeter,
+        ))
+        .with_children(|meter| {
+            meter.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.0.clone_weak(),
+                        font_size: 30.0,
+                        color: Color::ORANGE,
+                    },
+                ),
+                ComboText,
+            ));
+            meter
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Px(8.0),
+                        ..default()
+                    },
+                    background_color: Color::rgba(1.0, 1.0, 1.0, 0.2).into(),
+                    ..default()
+                })
+                .with_children(|bar| {
+                    bar.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(100.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            background_color: Color::ORANGE.into(),
+                            ..default()
+                        },
+                        ComboFill,
+                    ));
+                });
+        });
+}
+
+fn update_meter(
+    mut meter_q: Query<&mut Visibility, With<ComboMeter>>,
+    mut text_q: Query<&mut Text, With<ComboText>>,
+    mut fill_q: Query<&mut Style, With<ComboFill>>,
+    multiplier: Res<Multiplier>,
+    timer: Res<ComboTimer>,
+    params: Res<SimParams>,
+    locale: Res<Locale>,
+) {
+    let shown = if multiplier.0 > 0 {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    for mut visibility in meter_q.iter_mut() {
+        if *visibility != shown {
+            *visibility = shown;
+        }
+    }
+
+    if multiplier.is_changed() {
+        for mut text in text_q.iter_mut() {
+            text.sections[0].value =
+                locale.fmt("combo", &[("multiplier", multiplier.0.to_string())]);
+        }
+    }
+
+    // without a window combos last until the next drop, so there's nothing to run down
+    let left = if params.combo.window > 0.0 {
+        (timer.0 / params.combo.window).clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+    for mut style in fill_q.iter_mut() {
+        style.width = Val::Percent(left * 100.0);
+    }
+}
+
+fn show_bonus(
+    mut ended_er: EventReader<ComboEndedEvent>,
+    mut commands: Commands,
+    font: Res<CustomFont>,
+    locale: Res<Locale>,
+) {
+    for ev in ended_er.iter().filter(|ev| ev.bonus > 0) {
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(POPUP_TOP),
+                        width: Val::Percent(100.0),
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    z_index: ZIndex::Global(5),
+                    ..default()
+                },
+                BonusPopup(Timer::from_seconds(POPUP_SECS, TimerMode::Once)),
+                RunningTag,
+            ))
+            .with_children(|popup| {
+                popup.spawn(TextBundle::from_section(
+                    locale.fmt(
+                        "combo-bonus",
+                        &[
+                            ("merges", ev.merges.to_string()),
+                            ("bonus", ev.bonus.to_string()),
+                        ],
+                    ),
+                    TextStyle {
+                        font: font.0.clone_weak(),
+                        font_size: 36.0,
+                        color: Color::ORANGE,
+                    },
+                ));
+            });
+    }
+}
+
+fn float_bonus(
+    mut popup_q: Query<(Entity, &mut BonusPopup, &mut Style, &Children)>,
+    mut text_q: Query<&mut Text>,
+    mut commands: Commands,
+    settings: Res<Accessibility>,
+    time: Res<Time>,
+) {
+    for (entity, mut popup, mut style, children) in popup_q.iter_mut() {
+        popup.0.tick(time.delta());
+        if popup.0.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        if !settings.reduced_motion {
+            style.top = Val::Px(POPUP_TOP - POPUP_RISE * popup.0.percent());
+        }
+        for &child in children.iter() {
+            if let Ok(mut text) = text_q.get_mut(child) {
+                text.sections[0].style.color.set_a(popup.0.percent_left());
+            }
+        }
+    }
+}
@@ -0,0 +1,415 @@
+//! Developer tools. F3 toggles an overlay that draws what the physics sees (colliders, contact
+//! points, velocities, how far each ball's settle timer has got) and lists frame rate, quality and
+//! entity counts. The backtick key opens a console; `help` lists its commands.
+//!
+//! Using the console to change a run marks it with [`ConsoleUsed`], and marked runs can't be
+//! submitted to the leaderboard or earn achievements. Some commands would make the replay fail
+//! verification anyway, but `slowmo` doesn't, so the mark is what keeps them off.
+
+use bevy::{
+    diagnostic::{DiagnosticsStore, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin},
+    input::InputSystem,
+    prelude::*,
+};
+use bevy_xpbd_2d::prelude::*;
+use pumpkin_game::{
+    level::{Level, BUILT_IN},
+    sim::{
+        BallSize, RunConfig, Score, SettleTimer, SimParams, SpawnBallEvent, BALL_ORDER, DEATH_LINE,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    quality::{EffectsBudget, Quality, QualityController},
+    resume, CursorWorldPos, GameState,
+};
+
+/// Lines of console output kept on screen.
+const CONSOLE_LINES: usize = 8;
+/// Drawn velocity arrows are this many seconds of travel long.
+const VELOCITY_SCALE: f32 = 0.1;
+
+const HELP: &str = "spawn <tier>, score <n>, gravity, slowmo [speed], level [name], clear";
+
+pub struct DevPlugin;
+
+impl Plugin for DevPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((FrameTimeDiagnosticsPlugin, EntityCountDiagnosticsPlugin))
+            .init_resource::<DevTools>()
+            .init_resource::<ConsoleUsed>()
+            .add_systems(
+                OnEnter(GameState::Running),
+                mark_console_run.before(resume::restore_run),
+            )
+            .add_systems(OnEnter(GameState::GameOver), forget_picked_level)
+            // before anything in Update, so typing into the console doesn't also play the game
+            .add_systems(PreUpdate, console_input.after(InputSystem))
+            .add_systems(
+                Update,
+                (
+                    toggle_overlay,
+                    update_overlay,
+                    draw_colliders,
+                    draw_contacts,
+                    update_console,
+                ),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+struct DevTools {
+    console_open: bool,
+    input: String,
+    output: Vec<String>,
+    /// Whether the level in the run config was picked from the console. It's only for the next
+    /// run, so it goes back to the classic box once that's over.
+    level_picked: bool,
+}
+
+impl DevTools {
+    fn print(&mut self, line: impl Into<String>) {
+        self.output.push(line.into());
+        if self.output.len() > CONSOLE_LINES {
+            self.output.remove(0);
+        }
+    }
+}
+
+/// Whether the run in progress has been changed from the console.
+#[derive(Resource, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ConsoleUsed(pub bool);
+
+/// Starts each run unmarked, unless it's starting in slow motion or in a level picked from the
+/// console.
+fn mark_console_run(mut used: ResMut<ConsoleUsed>, dev: Res<DevTools>, time: Res<Time>) {
+    used.0 = time.relative_speed() != 1.0 || dev.level_picked;
+}
+
+fn forget_picked_level(mut dev: ResMut<DevTools>, mut config: ResMut<RunConfig>) {
+    if std::mem::take(&mut dev.level_picked) {
+        config.level = Level::default();
+    }
+}
+
+#[derive(Component)]
+struct DevOverlay;
+
+#[derive(Component)]
+struct DevConsole;
+
+fn overlay_text(value: &str, style: Style) -> TextBundle {
+    TextBundle::from_section(
+        value,
+        TextStyle {
+            font_size: 16.0,
+            color: Color::WHITE,
+            ..default()
+        },
+    )
+    .with_style(Style {
+        position_type: PositionType::Absolute,
+        ..style
+    })
+    .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.6))
+}
+
+fn toggle_overlay(
+    keys: Res<Input<KeyCode>>,
+    overlay_q: Query<Entity, With<DevOverlay>>,
+    mut commands: Commands,
+) {
+    if !keys.just_pressed(KeyCode::F3) {
+        return;
+    }
+
+    if let Ok(entity) = overlay_q.get_single() {
+        commands.entity(entity).despawn_recursive();
+        return;
+    }
+
+    commands.spawn((
+        overlay_text(
+            "",
+            Style {
+                bottom: Val::Px(5.0),
+                left: Val::Px(5.0),
+                ..default()
+            },
+        ),
+        DevOverlay,
+    ));
+}
+
+fn update_overlay(
+    mut overlay_q: Query<&mut Text, With<DevOverlay>>,
+    diagnostics: Res<DiagnosticsStore>,
+    ball_q: Query<&SettleTimer, With<BallSize>>,
+    controller: Res<QualityController>,
+    quality: Res<Quality>,
+    budget: Res<EffectsBudget>,
+    substeps: Res<SubstepCount>,
+) {
+    let Ok(mut text) = overlay_q.get_single_mut() else {
+        return;
+    };
+
+    let measure = |id| {
+        diagnostics
+            .get(id)
+            .and_then(|d| d.smoothed())
+            .unwrap_or_default()
+    };
+
+    let settling = ball_q.iter().filter(|t| t.0.elapsed_secs() > 0.0).count();
+    let closest = ball_q.iter().map(|t| t.0.percent()).fold(0.0, f32::max);
+
+    text.sections[0].value = format!(
+        "{:.0} fps  {} entities  {} balls\n\
+        {} over the line, closest to ending the run {:.0}%\n\
+        quality {}  substeps {}  effects {:.0}%\n{}",
+        measure(FrameTimeDiagnosticsPlugin::FPS),
+        measure(EntityCountDiagnosticsPlugin::ENTITY_COUNT),
+        ball_q.iter().count(),
+        settling,
+        closest * 100.0,
+        quality.0,
+        substeps.0,
+        budget.0 * 100.0,
+        controller.report(),
+    );
+}
+
+/// Outlines every collider, balls going from green to red as their settle timer runs out, with
+/// a line showing where each is heading.
+fn draw_colliders(
+    overlay_q: Query<(), With<DevOverlay>>,
+    ball_q: Query<(&Position, &LinearVelocity, &BallSize, &SettleTimer)>,
+    static_q: Query<&ColliderAabb, Without<BallSize>>,
+    params: Res<SimParams>,
+    mut gizmos: Gizmos,
+) {
+    if overlay_q.is_empty() {
+        return;
+    }
+
+    for (pos, velocity, size, timer) in ball_q.iter() {
+        let settle = timer.0.percent();
+        gizmos.circle_2d(
+            pos.0,
+            params.radius(size.0),
+            Color::rgb(settle, 1.0 - settle, 0.0),
+        );
+        gizmos.line_2d(pos.0, pos.0 + velocity.0 * VELOCITY_SCALE, Color::CYAN);
+    }
+
+    for aabb in static_q.iter() {
+        let min = Vec2::new(aabb.mins.x, aabb.mins.y);
+        let max = Vec2::new(aabb.maxs.x, aabb.maxs.y);
+        gizmos.rect_2d((min + max) / 2.0, 0.0, max - min, Color::YELLOW);
+    }
+
+    gizmos.line_2d(
+        Vec2::new(-10.0, DEATH_LINE),
+        Vec2::new(10.0, DEATH_LINE),
+        Color::RED,
+    );
+}
+
+/// Marks where things are touching. Balls against balls touch on the line between their
+/// centres; against the walls, at the nearest point of the wall's box.
+fn draw_contacts(
+    overlay_q: Query<(), With<DevOverlay>>,
+    mut collision_er: EventReader<Collision>,
+    ball_q: Query<(&Position, &BallSize)>,
+    static_q: Query<&ColliderAabb, Without<BallSize>>,
+    params: Res<SimParams>,
+    mut gizmos: Gizmos,
+) {
+    if overlay_q.is_empty() {
+        collision_er.clear();
+        return;
+    }
+
+    for Collision(contact) in collision_er.iter() {
+        let point = match (ball_q.get(contact.entity1), ball_q.get(contact.entity2)) {
+            (Ok((pos1, size1)), Ok((pos2, _))) => {
+                pos1.0 + (pos2.0 - pos1.0).normalize_or_zero() * params.radius(size1.0)
+            }
+            (Ok((pos, _)), Err(_)) | (Err(_), Ok((pos, _))) => {
+                let wall = static_q
+                    .get(contact.entity1)
+                    .or_else(|_| static_q.get(contact.entity2));
+                let Ok(aabb) = wall else {
+                    continue;
+                };
+                pos.0.clamp(
+                    Vec2::new(aabb.mins.x, aabb.mins.y),
+                    Vec2::new(aabb.maxs.x, aabb.maxs.y),
+                )
+            }
+            _ => continue,
+        };
+        gizmos.circle_2d(point, 0.04, Color::FUCHSIA);
+    }
+}
+
+/// Reads keys while the console is open and keeps them from reaching the game.
+fn console_input(
+    mut dev: ResMut<DevTools>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut chars_er: EventReader<ReceivedCharacter>,
+    mut commands: Commands,
+) {
+    if keys.just_pressed(KeyCode::Grave) {
+        dev.console_open = !dev.console_open;
+        chars_er.clear();
+        keys.clear();
+        return;
+    }
+
+    if !dev.console_open {
+        chars_er.clear();
+        return;
+    }
+
+    for ev in chars_er.iter() {
+        if !ev.char.is_control() && ev.char != '`' {
+            dev.input.push(ev.char);
+        }
+    }
+
+    if keys.just_pressed(KeyCode::Back) {
+        dev.input.pop();
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        dev.console_open = false;
+    }
+    if keys.just_pressed(KeyCode::Return) {
+        let line = std::mem::take(&mut dev.input);
+        dev.print(format!("> {}", line));
+        commands.add(move |world: &mut World| {
+            let command = line.split_whitespace().next().unwrap_or_default();
+            if matches!(command, "spawn" | "score" | "gravity" | "slowmo") {
+                world.resource_mut::<ConsoleUsed>().0 = true;
+            }
+            let reply = run_command(world, &line);
+            let mut dev = world.resource_mut::<DevTools>();
+            if line.trim() == "clear" {
+                dev.output.clear();
+            } else if !reply.is_empty() {
+                dev.print(reply);
+            }
+        });
+    }
+
+    keys.clear();
+}
+
+/// Runs one console line, returning what to print.
+fn run_command(world: &mut World, line: &str) -> String {
+    let mut words = line.split_whitespace();
+    let Some(command) = words.next() else {
+        return String::new();
+    };
+    let arg = words.next();
+
+    match command {
+        "help" => HELP.into(),
+        "clear" => String::new(),
+        "spawn" => {
+            let Some(size) = arg.and_then(|a| a.parse::<usize>().ok()) else {
+                return "spawn needs a tier".into();
+            };
+            if size >= BALL_ORDER.len() {
+                return format!("tiers go up to {}", BALL_ORDER.len() - 1);
+            }
+            let position = world.resource::<CursorWorldPos>().0;
+            world
+                .resource_mut::<Events<SpawnBallEvent>>()
+                .send(SpawnBallEvent {
+                    position,
+                    size,
+                    av: 0.0,
+                });
+            format!("spawned {}", BALL_ORDER[size])
+        }
+        "score" => {
+            let Some(score) = arg.and_then(|a| a.parse().ok()) else {
+                return "score needs a number".into();
+            };
+            world.resource_mut::<Score>().0 = score;
+            format!("score set to {}", score)
+        }
+        "gravity" => {
+            let strength = world.resource::<SimParams>().gravity;
+            let mut gravity = world.resource_mut::<Gravity>();
+            if gravity.0 == Vec2::ZERO {
+                gravity.0 = Vec2::NEG_Y * strength;
+                "gravity on".into()
+            } else {
+                gravity.0 = Vec2::ZERO;
+                "gravity off".into()
+            }
+        }
+        "slowmo" => {
+            let speed = match arg.map(str::parse::<f32>) {
+                None => 1.0,
+                Some(Ok(speed)) if speed > 0.0 => speed,
+                Some(_) => return "slowmo takes a speed above 0".into(),
+            };
+            world.resource_mut::<Time>().set_relative_speed(speed);
+            format!("running at {}x", speed)
+        }
+        "level" => {
+            let Some(name) = arg else {
+                let names: Vec<&str> = BUILT_IN.iter().map(|(name, _)| *name).collect();
+                return format!("levels: classic, {}", names.join(", "));
+            };
+            let level = match Level::built_in(name) {
+                Some(level) => level,
+                None if name == "classic" => Level::default(),
+                None => return format!("no level called {}", name),
+            };
+            world.resource_mut::<DevTools>().level_picked = level != Level::default();
+            world.resource_mut::<RunConfig>().level = level;
+            format!("next run is in {}", name)
+        }
+        _ => format!("unknown command {}, try help", command),
+    }
+}
+
+fn update_console(
+    dev: Res<DevTools>,
+    mut console_q: Query<(Entity, &mut Text), With<DevConsole>>,
+    mut commands: Commands,
+) {
+    if !dev.is_changed() {
+        return;
+    }
+
+    let mut value = dev.output.join("\n");
+    value.push_str(&format!("\n> {}_", dev.input));
+
+    match (dev.console_open, console_q.get_single_mut()) {
+        (true, Ok((_, mut text))) => text.sections[0].value = value,
+        (true, Err(_)) => {
+            commands.spawn((
+                overlay_text(
+                    &value,
+                    Style {
+                        top: Val::Px(5.0),
+                        left: Val::Px(5.0),
+                        right: Val::Px(5.0),
+                        ..default()
+                    },
+                ),
+                DevConsole,
+            ));
+        }
+        (false, Ok((entity, _))) => commands.entity(entity).despawn_recursive(),
+        (false, Err(_)) => {}
+    }
+}
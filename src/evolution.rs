@@ -0,0 +1,108 @@
+//! The order balls merge up in, as a strip of every size from smallest to biggest. In a run,
+//! sizes not reached yet are dimmed and the biggest so far is outlined.
+
+use bevy::prelude::*;
+use pumpkin_game::sim::{BallSize, SimEntity, BALL_ORDER};
+
+use crate::{stats::RunStats, BallAtlas, GameState};
+
+/// How see-through sizes not reached yet are.
+const UNREACHED_ALPHA: f32 = 0.3;
+
+pub struct EvolutionPlugin;
+
+impl Plugin for EvolutionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, highlight_strip.run_if(in_state(GameState::Running)));
+    }
+}
+
+/// One size's place in a strip.
+#[derive(Component)]
+struct StripSlot {
+    size: usize,
+}
+
+/// Adds a strip of every size under `parent`, `icon_size` pixels a ball. It follows the run when
+/// spawned in [`GameState::Running`], and shows every size alike anywhere else.
+pub fn spawn_strip(parent: &mut ChildBuilder, ball_atlas: &BallAtlas, icon_size: f32) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                flex_wrap: FlexWrap::Wrap,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|strip| {
+            for size in 0..BALL_ORDER.len() {
+                strip
+                    .spawn((
+                        NodeBundle {
+                            style: Style {
+                                border: UiRect::all(Val::Px(2.0)),
+                                padding: UiRect::all(Val::Px(1.0)),
+                                ..default()
+                            },
+                            border_color: Color::NONE.into(),
+                            ..default()
+                        },
+                        StripSlot { size },
+                    ))
+                    .with_children(|slot| {
+                        slot.spawn(AtlasImageBundle {
+                            style: Style {
+                                width: Val::Px(icon_size),
+                                height: Val::Px(icon_size),
+                                ..default()
+                            },
+                            texture_atlas: ball_atlas.0.clone_weak(),
+                            texture_atlas_image: UiTextureAtlasImage {
+                                index: size,
+                                ..default()
+                            },
+                            ..default()
+                        });
+                    });
+            }
+        });
+}
+
+fn highlight_strip(
+    mut slot_q: Query<(&StripSlot, &mut BorderColor, &Children)>,
+    mut image_q: Query<&mut BackgroundColor>,
+    ball_q: Query<&BallSize, With<SimEntity>>,
+    run_stats: Res<RunStats>,
+) {
+    // a resumed run starts its stats over, but its board still shows how far it got
+    let highest = ball_q
+        .iter()
+        .map(|size| size.0)
+        .fold(run_stats.largest_tier, usize::max);
+
+    for (slot, mut border, children) in slot_q.iter_mut() {
+        let outline = if slot.size == highest {
+            Color::ORANGE
+        } else {
+            Color::NONE
+        };
+        if border.0 != outline {
+            border.0 = outline;
+        }
+
+        let tint = if slot.size <= highest {
+            Color::WHITE
+        } else {
+            Color::rgba(1.0, 1.0, 1.0, UNREACHED_ALPHA)
+        };
+        for &child in children.iter() {
+            if let Ok(mut background) = image_q.get_mut(child) {
+                if background.0 != tint {
+                    background.0 = tint;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! The how to play page, off the splash screen.
+
+use bevy::prelude::*;
+use pumpkin_game::sim::SimParams;
+
+use crate::{
+    evolution, layout::SafeArea, locale::Locale, spawn_button, BallAtlas, CustomFont, GameState,
+};
+
+pub struct HelpPlugin;
+
+impl Plugin for HelpPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, help_button)
+            .add_systems(OnEnter(GameState::HowToPlay), build_help)
+            .add_systems(OnExit(GameState::HowToPlay), crate::despawn_with::<HelpTag>)
+            .add_systems(Update, back_button.run_if(in_state(GameState::HowToPlay)));
+    }
+}
+
+#[derive(Component)]
+pub struct HelpButton;
+
+#[derive(Component)]
+struct BackButton;
+
+#[derive(Component)]
+struct HelpTag;
+
+fn help_button(
+    button_q: Query<&Interaction, (Changed<Interaction>, With<HelpButton>)>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if button_q.iter().any(|i| *i == Interaction::Pressed) {
+        next_state.0 = Some(GameState::HowToPlay);
+    }
+}
+
+fn back_button(
+    button_q: Query<&Interaction, (Changed<Interaction>, With<BackButton>)>,
+    keys: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keys.just_pressed(KeyCode::Escape) || button_q.iter().any(|i| *i == Interaction::Pressed) {
+        next_state.0 = Some(GameState::Splash);
+    }
+}
+
+fn build_help(
+    mut commands: Commands,
+    font: Res<CustomFont>,
+    locale: Res<Locale>,
+    ball_atlas: Res<BallAtlas>,
+    params: Res<SimParams>,
+) {
+    let text = |value: String, font_size: f32| TextSection {
+        value,
+        style: TextStyle {
+            font: font.0.clone_weak(),
+            font_size,
+            color: Color::WHITE,
+        },
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    flex_wrap: FlexWrap::Wrap,
+                    padding: UiRect::top(Val::Px(30.0)),
+                    ..default()
+                },
+                background_color: Color::rgb_u8(52, 52, 52).into(),
+                ..default()
+            },
+            HelpTag,
+            SafeArea::default(),
+        ))
+        .with_children(|root| {
+            root.spawn(
+                TextBundle::from_sections([
+                    text(format!("{}\n\n", locale.get("how-to-play")), 40.0),
+                    text(format!("{}\n", locale.get("help-drop")), 24.0),
+                    text(format!("{}\n", locale.get("help-merge")), 24.0),
+                    text(format!("{}\n", locale.get("help-line")), 24.0),
+                    text(
+                        format!(
+                            "{}\n\n",
+                            locale.fmt(
+                                "help-combo",
+                                &[
+                                    ("window", format!("{:.1}", params.combo.window)),
+                                    ("bonus", params.combo.end_bonus.to_string()),
+                                ],
+                            )
+                        ),
+                        24.0,
+                    ),
+                    text(locale.get("help-order"), 24.0),
+                ])
+                .with_text_alignment(TextAlignment::Center),
+            );
+            evolution::spawn_strip(root, &ball_atlas, 32.0);
+            root.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|button_box| {
+                spawn_button(
+                    button_box,
+                    &font,
+                    &locale,
+                    "back",
+                    (Color::BLUE, Color::MIDNIGHT_BLUE),
+                    150.0,
+                    BackButton,
+                );
+            });
+        });
+}
@@ -0,0 +1,262 @@
+//! Physical feedback for the board: the camera shakes with big merges and hard knocks, big merges
+//! freeze the game for a moment, longer the bigger they are, and phones that can buzz do. None of
+//! it happens with reduced motion on.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+use pumpkin_game::sim::{BallSize, BallsMergedEvent, BALL_ORDER};
+
+use crate::{accessibility::Accessibility, GameState};
+
+/// How far the camera moves at full trauma, in world units.
+const MAX_OFFSET: f32 = 0.25;
+/// Trauma lost per second. Shake goes with trauma squared, so it dies away quicker than this.
+const TRAUMA_DECAY: f32 = 1.5;
+/// How fast the shake wobbles.
+const SHAKE_FREQUENCY: f32 = 30.0;
+/// Trauma from a merge into the biggest ball. Smaller merges give less, by the square of their
+/// tier.
+const MERGE_TRAUMA: f32 = 0.6;
+/// Knocks softer than this, in mass times speed along the contact, don't shake anything.
+const IMPULSE_THRESHOLD: f32 = 5.0;
+/// Trauma per unit of impulse over the threshold.
+const IMPULSE_TRAUMA: f32 = 0.02;
+/// The most one knock can add, so a pile settling doesn't shake like a pumpkin.
+const MAX_IMPULSE_TRAUMA: f32 = 0.3;
+/// Hit-stop for a merge into the biggest ball. Smaller merges stop for less, by the square of
+/// their tier, like the shake.
+const MAX_HIT_STOP_SECS: f32 = 0.1;
+/// Stops shorter than this wouldn't be noticed, so small merges don't stop at all.
+const MIN_HIT_STOP_SECS: f32 = 0.02;
+/// Buzz length for a merge into the biggest ball, in milliseconds, scaled the same way.
+const MAX_VIBRATE_MS: f32 = 80.0;
+
+pub struct ImpactPlugin;
+
+impl Plugin for ImpactPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Shake>()
+            .init_resource::<HitStop>()
+            .add_systems(
+                Update,
+                (merge_impact, collision_impact).run_if(in_state(GameState::Running)),
+            )
+            .add_systems(Update, (shake_camera, end_hit_stop));
+    }
+}
+
+#[derive(Resource, Default)]
+struct Shake {
+    /// From 0 for still to 1 for shaking as hard as it goes.
+    trauma: f32,
+    /// Seconds of shake so far, for the wobble.
+    clock: f32,
+}
+
+impl Shake {
+    fn add(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).min(1.0);
+    }
+}
+
+/// The sim is stopped by holding back its fixed timestep, rather than by slowing [`Time`], so
+/// the game speed the dev console may have set is left alone.
+#[derive(Resource, Default)]
+struct HitStop {
+    /// Real seconds left, while the game is stopped.
+    left: Option<f32>,
+    /// The sim's step, and the time it had built up towards the next one, to go back to.
+    held: Option<(Duration, Duration)>,
+}
+
+fn merge_impact(
+    mut merged_er: EventReader<BallsMergedEvent>,
+    mut shake: ResMut<Shake>,
+    mut hit_stop: ResMut<HitStop>,
+    mut fixed: ResMut<FixedTime>,
+    settings: Res<Accessibility>,
+) {
+    let Some(biggest) = merged_er.iter().map(|ev| ev.size).max() else {
+        return;
+    };
+    if settings.reduced_motion {
+        return;
+    }
+
+    let tier = biggest as f32 / (BALL_ORDER.len() - 1) as f32;
+    shake.add(MERGE_TRAUMA * tier * tier);
+
+    let secs = MAX_HIT_STOP_SECS * tier * tier;
+    if secs >= MIN_HIT_STOP_SECS {
+        if hit_stop.held.is_none() {
+            hit_stop.held = Some((fixed.period, fixed.accumulated()));
+            // time still builds up, but never to a whole step
+            fixed.period = Duration::MAX;
+        }
+        hit_stop.left = Some(hit_stop.left.unwrap_or_default().max(secs));
+        vibrate((MAX_VIBRATE_MS * tier * tier).round() as u32);
+    }
+}
+
+fn collision_impact(
+    mut collision_er: EventReader<Collision>,
+    ball_q: Query<(&LinearVelocity, &Mass), With<BallSize>>,
+    mut shake: ResMut<Shake>,
+    settings: Res<Accessibility>,
+) {
+    if settings.reduced_motion {
+        collision_er.clear();
+        return;
+    }
+
+    let mut hardest = 0.0f32;
+    for Collision(contact) in collision_er.iter() {
+        let ball1 = ball_q.get(contact.entity1).ok();
+        let ball2 = ball_q.get(contact.entity2).ok();
+        // the walls and floor don't move and can't be pushed, so only the ball's own mass counts
+        let (velocity, mass) = match (ball1, ball2) {
+            (Some((v1, m1)), Some((v2, m2))) => (v1.0 - v2.0, m1.0 * m2.0 / (m1.0 + m2.0)),
+            (Some((v, m)), None) => (v.0, m.0),
+            (None, Some((v, m))) => (v.0, m.0),
+            (None, None) => continue,
+        };
+        hardest = hardest.max(velocity.dot(contact.normal).abs() * mass);
+    }
+
+    if hardest > IMPULSE_THRESHOLD {
+        shake.add(((hardest - IMPULSE_THRESHOLD) * IMPULSE_TRAUMA).min(MAX_IMPULSE_TRAUMA));
+    }
+}
+
+/// Runs on real time, so slow motion from the dev console doesn't slow the shake.
+fn shake_camera(
+    mut camera_q: Query<&mut Transform, With<Camera2d>>,
+    mut shake: ResMut<Shake>,
+    time: Res<Time>,
+) {
+    if shake.trauma <= 0.0 {
+        shake.clock = 0.0;
+        for mut transform in camera_q.iter_mut() {
+            if transform.translation.x != 0.0 || transform.translation.y != 0.0 {
+                transform.translation.x = 0.0;
+                transform.translation.y = 0.0;
+            }
+        }
+        return;
+    }
+
+    let dt = time.raw_delta_seconds();
+    shake.clock += dt;
+    let t = shake.clock * SHAKE_FREQUENCY;
+    // a few sines at odd ratios wander enough not to look like a pattern
+    let wobble = Vec2::new(
+        (t.sin() + (t * 2.3 + 1.7).sin() * 0.5) / 1.5,
+        ((t * 1.3 + 0.5).sin() + (t * 3.1).sin() * 0.5) / 1.5,
+    );
+    let offset = wobble * MAX_OFFSET * shake.trauma * shake.trauma;
+    for mut transform in camera_q.iter_mut() {
+        transform.translation.x = offset.x;
+        transform.translation.y = offset.y;
+    }
+
+    shake.trauma = (shake.trauma - TRAUMA_DECAY * dt).max(0.0);
+}
+
+fn end_hit_stop(mut hit_stop: ResMut<HitStop>, mut fixed: ResMut<FixedTime>, time: Res<Time>) {
+    let Some(left) = hit_stop.left else {
+        return;
+    };
+    let left = left - time.raw_delta_seconds();
+    if left > 0.0 {
+        hit_stop.left = Some(left);
+        return;
+    }
+
+    hit_stop.left = None;
+    if let Some((period, accumulated)) = hit_stop.held.take() {
+        // what built up while stopped is dropped, so the sim doesn't rush to catch up
+        *fixed = FixedTime::new(period);
+        fixed.tick(accumulated);
+    }
+}
+
+/// Buzzes the phone, on browsers that can.
+#[cfg(target_arch = "wasm32")]
+fn vibrate(ms: u32) {
+    if let Some(window) = web_sys::window() {
+        window.navigator().vibrate_with_duration(ms);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn vibrate(_ms: u32) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.init_resource::<Events<BallsMergedEvent>>();
+        world.init_resource::<Time>();
+        world.init_resource::<Shake>();
+        world.init_resource::<HitStop>();
+        world.insert_resource(Accessibility::default());
+        world.insert_resource(FixedTime::new_from_secs(1.0 / 60.0));
+        world
+    }
+
+    fn run<M>(world: &mut World, system: impl IntoSystemConfigs<M>) {
+        let mut schedule = Schedule::new();
+        schedule.add_systems(system);
+        schedule.run(world);
+    }
+
+    fn merge(world: &mut World, size: usize) {
+        world.send_event(BallsMergedEvent {
+            position: Vec2::ZERO,
+            size,
+            multiplier: 1,
+            from: [(Vec2::ZERO, 0.0); 2],
+        });
+        run(world, merge_impact);
+    }
+
+    #[test]
+    fn small_merges_dont_stop() {
+        let mut world = world();
+        merge(&mut world, 1);
+        assert!(world.resource::<HitStop>().left.is_none());
+        assert_eq!(
+            world.resource::<FixedTime>().period,
+            Duration::from_secs_f32(1.0 / 60.0)
+        );
+    }
+
+    #[test]
+    fn stops_hold_the_sim_and_leave_its_speed_alone() {
+        let mut world = world();
+        world.resource_mut::<Time>().set_relative_speed(0.5);
+        world
+            .resource_mut::<FixedTime>()
+            .tick(Duration::from_millis(5));
+
+        merge(&mut world, BALL_ORDER.len() - 1);
+        assert_eq!(world.resource::<FixedTime>().period, Duration::MAX);
+        assert_eq!(world.resource::<HitStop>().left, Some(MAX_HIT_STOP_SECS));
+
+        // time piling up while stopped doesn't carry over
+        world
+            .resource_mut::<FixedTime>()
+            .tick(Duration::from_millis(50));
+        world.resource_mut::<HitStop>().left = Some(0.0);
+        run(&mut world, end_hit_stop);
+
+        let fixed = world.resource::<FixedTime>();
+        assert_eq!(fixed.period, Duration::from_secs_f32(1.0 / 60.0));
+        assert_eq!(fixed.accumulated(), Duration::from_millis(5));
+        assert_eq!(world.resource::<Time>().relative_speed(), 0.5);
+    }
+}
@@ -0,0 +1,165 @@
+//! Fits the UI to the screen. Everything is laid out for a 480 x 720 window and scaled from
+//! there, on top of the player's chosen text size. Window sizes are in logical pixels, so the
+//! display's DPI is already accounted for. Wide windows get the HUD down the side instead of
+//! across the top, and screens keep their content out from under notches and home indicators.
+
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::accessibility::Accessibility;
+
+/// The window size the UI's pixel sizes were picked for.
+const DESIGN_SIZE: Vec2 = Vec2::new(480.0, 720.0);
+const MIN_SCALE: f32 = 0.75;
+const MAX_SCALE: f32 = 3.0;
+
+pub struct LayoutPlugin;
+
+impl Plugin for LayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScreenLayout>().add_systems(
+            Update,
+            (measure_screen, (apply_safe_area, orient_hud)).chain(),
+        );
+    }
+}
+
+/// Space at each edge of the window that's covered by the device, in logical pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Insets {
+    top: f32,
+    right: f32,
+    bottom: f32,
+    left: f32,
+}
+
+#[derive(Resource, Debug, PartialEq)]
+struct ScreenLayout {
+    /// What the UI is multiplied by, text size included.
+    scale: f32,
+    landscape: bool,
+    insets: Insets,
+}
+
+impl Default for ScreenLayout {
+    fn default() -> Self {
+        ScreenLayout {
+            scale: 1.0,
+            landscape: false,
+            insets: Insets::default(),
+        }
+    }
+}
+
+/// A full screen node whose padding is kept clear of the [`Insets`]. Its own padding is added
+/// to, so anything spawned with this should use pixel padding.
+#[derive(Component, Default)]
+pub struct SafeArea {
+    base: Option<UiRect>,
+}
+
+/// The in-game HUD, laid out across the top in portrait and down the side in landscape.
+#[derive(Component)]
+pub struct Hud;
+
+fn measure_screen(
+    window_q: Query<Ref<Window>, With<PrimaryWindow>>,
+    settings: Res<Accessibility>,
+    mut layout: ResMut<ScreenLayout>,
+    mut ui_scale: ResMut<UiScale>,
+) {
+    let Ok(window) = window_q.get_single() else {
+        return;
+    };
+    if !window.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    let size = Vec2::new(window.width(), window.height());
+    let fit = (size / DESIGN_SIZE)
+        .min_element()
+        .clamp(MIN_SCALE, MAX_SCALE);
+
+    layout.set_if_neq(ScreenLayout {
+        scale: fit * settings.text_scale,
+        landscape: size.x > size.y,
+        insets: safe_area_insets(),
+    });
+    ui_scale.scale = layout.scale as f64;
+}
+
+#[cfg(target_arch = "wasm32")]
+fn safe_area_insets() -> Insets {
+    // the page pads a hidden element by the env(safe-area-inset-*) values, which is the only
+    // way to read them
+    let read = || {
+        let window = web_sys::window()?;
+        let probe = window.document()?.get_element_by_id("safe-area")?;
+        let style = window.get_computed_style(&probe).ok()??;
+        let px = |side: &str| {
+            style
+                .get_property_value(&format!("padding-{}", side))
+                .ok()
+                .and_then(|value| value.trim_end_matches("px").parse().ok())
+                .unwrap_or(0.0)
+        };
+        Some(Insets {
+            top: px("top"),
+            right: px("right"),
+            bottom: px("bottom"),
+            left: px("left"),
+        })
+    };
+    read().unwrap_or_default()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn safe_area_insets() -> Insets {
+    Insets::default()
+}
+
+fn apply_safe_area(
+    mut node_q: Query<(&mut Style, &mut SafeArea)>,
+    added_q: Query<(), Added<SafeArea>>,
+    layout: Res<ScreenLayout>,
+) {
+    if !layout.is_changed() && added_q.is_empty() {
+        return;
+    }
+
+    // insets are real pixels, but UI pixels get scaled
+    let pad = |base: Val, inset: f32| match base {
+        Val::Px(px) => Val::Px(px + inset / layout.scale),
+        Val::Auto => Val::Px(inset / layout.scale),
+        other => other,
+    };
+
+    for (mut style, mut safe_area) in node_q.iter_mut() {
+        let base = *safe_area.base.get_or_insert(style.padding);
+        style.padding = UiRect {
+            left: pad(base.left, layout.insets.left),
+            right: pad(base.right, layout.insets.right),
+            top: pad(base.top, layout.insets.top),
+            bottom: pad(base.bottom, layout.insets.bottom),
+        };
+    }
+}
+
+fn orient_hud(
+    mut hud_q: Query<&mut Style, With<Hud>>,
+    added_q: Query<(), Added<Hud>>,
+    layout: Res<ScreenLayout>,
+) {
+    if !layout.is_changed() && added_q.is_empty() {
+        return;
+    }
+
+    for mut style in hud_q.iter_mut() {
+        if layout.landscape {
+            style.flex_direction = FlexDirection::Column;
+            style.align_items = AlignItems::FlexStart;
+        } else {
+            style.flex_direction = FlexDirection::Row;
+            style.align_items = AlignItems::Stretch;
+        }
+    }
+}
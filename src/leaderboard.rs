@@ -0,0 +1,39 @@
+//! What the game and the leaderboard server say to each other, as JSON over HTTP.
+//!
+//! `POST /scores` takes a [`Submission`] and answers with a [`SubmitResponse`].
+//! `GET /scores?mode=classic` answers with the top [`LeaderboardEntry`]s for that mode.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{level::Level, replay::Replay, sim::GameMode};
+
+pub const MAX_NAME_LEN: usize = 24;
+
+/// Whether runs like `replay` go on the leaderboard. The game checks before offering to submit
+/// one, and the server again before simulating it. Only classic and chaos runs in the classic box
+/// are ranked, as a replay can bring any level it likes.
+pub fn is_ranked(replay: &Replay) -> bool {
+    matches!(replay.mode, GameMode::Classic | GameMode::Chaos) && replay.level == Level::default()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Submission {
+    pub name: String,
+    pub score: usize,
+    pub replay: Replay,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SubmitResponse {
+    Accepted { rank: usize },
+    Rejected { reason: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub score: usize,
+    pub seed: u64,
+    pub mode: GameMode,
+}
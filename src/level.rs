@@ -0,0 +1,446 @@
+//! The box a run is played in. A [`Level`] says how wide and deep the box is and how its floor
+//! slopes, and lists anything else in it: pegs, bars, funnels and spinners. It can also start the
+//! run with balls already on the board and deal a fixed queue of balls instead of random ones.
+//! Levels are written in TOML, like the ones under `levels/` in the repo; the default is the
+//! classic box.
+//!
+//! Positions in a level are in world units, x from the middle of the box and y up from its floor.
+
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::sim::{
+    SimEntity, SimParams, BALL_ORDER, BOX_HEIGHT, BOX_WIDTH, MAX_RADIUS, TOP_OFFSET, WALL_THICKNESS,
+};
+
+/// How thick bars, funnels and spinners are unless they say otherwise.
+pub const BAR_THICKNESS: f32 = 0.1;
+/// Steepest a floor may slope, in degrees.
+pub const MAX_FLOOR_SLOPE: f32 = 30.0;
+
+/// The levels that come with the game, by name.
+pub const BUILT_IN: &[(&str, &str)] = &[
+    ("slope", include_str!("../levels/slope.toml")),
+    ("pegs", include_str!("../levels/pegs.toml")),
+    ("funnel", include_str!("../levels/funnel.toml")),
+    ("windmill", include_str!("../levels/windmill.toml")),
+];
+
+/// The box for the run in progress, or the one the next run will be played in as part of its
+/// [`RunConfig`].
+///
+/// [`RunConfig`]: crate::sim::RunConfig
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Level {
+    /// Between the middles of the side walls, as [`BOX_WIDTH`] is for the classic box.
+    pub width: f32,
+    /// From the floor to the top of the sides. The top stays put, just under the death line, so
+    /// a deeper box has its floor further down.
+    pub height: f32,
+    /// Degrees the floor is tipped, up on the right for positive.
+    pub floor_slope: f32,
+    pub obstacles: Vec<Obstacle>,
+    /// Balls on the board when the run starts.
+    pub balls: Vec<PlacedBall>,
+    /// Sizes of the balls to deal, in order. Once they've all been dropped there are no more.
+    /// Left empty, balls are dealt at random for as long as the run lasts.
+    pub queue: Vec<usize>,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Level {
+            width: BOX_WIDTH,
+            height: BOX_HEIGHT,
+            floor_slope: 0.0,
+            obstacles: Vec::new(),
+            balls: Vec::new(),
+            queue: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Obstacle {
+    Peg {
+        at: [f32; 2],
+        radius: f32,
+    },
+    /// A straight bar, for shelves and ramps.
+    Bar {
+        from: [f32; 2],
+        to: [f32; 2],
+        #[serde(default = "bar_thickness")]
+        thickness: f32,
+    },
+    /// A bar sloping down from each wall, leaving a gap of `gap` in the middle at height `y`.
+    Funnel {
+        y: f32,
+        gap: f32,
+        depth: f32,
+    },
+    /// A bar turning about its middle at `speed` radians a second, anticlockwise for positive.
+    Spinner {
+        at: [f32; 2],
+        length: f32,
+        speed: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PlacedBall {
+    pub at: [f32; 2],
+    pub size: usize,
+}
+
+fn bar_thickness() -> f32 {
+    BAR_THICKNESS
+}
+
+/// What a part of a level looks like, for the client to draw. The classic box's walls and flat
+/// floor are part of the background art, so don't have one.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub enum Piece {
+    Disc { radius: f32 },
+    Bar { length: f32, thickness: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LevelError {
+    Parse(String),
+    /// Too narrow for the biggest ball, too shallow, or a floor too steep.
+    BadBox,
+    BadObstacle(usize),
+    /// Not a size there is, or somewhere it can't be.
+    BadBall(usize),
+    /// The queue deals a size there isn't.
+    BadQueue,
+}
+
+impl std::fmt::Display for LevelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LevelError::Parse(e) => write!(f, "couldn't read level: {}", e),
+            LevelError::BadBox => write!(f, "the box can't be played in"),
+            LevelError::BadObstacle(i) => write!(f, "obstacle {} is invalid", i),
+            LevelError::BadBall(i) => write!(f, "ball {} is invalid", i),
+            LevelError::BadQueue => write!(f, "the queue deals a ball that doesn't exist"),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+impl Level {
+    pub fn from_toml(source: &str) -> Result<Self, LevelError> {
+        let level: Level = toml::from_str(source).map_err(|e| LevelError::Parse(e.to_string()))?;
+        level.check()?;
+        Ok(level)
+    }
+
+    /// One of [`BUILT_IN`].
+    pub fn built_in(name: &str) -> Option<Self> {
+        let (_, source) = BUILT_IN.iter().find(|(n, _)| *n == name)?;
+        Level::from_toml(source).ok()
+    }
+
+    pub fn check(&self) -> Result<(), LevelError> {
+        let box_ok = [self.width, self.height, self.floor_slope]
+            .iter()
+            .all(|v| v.is_finite())
+            && self.width >= MAX_RADIUS * 2.0 + WALL_THICKNESS
+            && self.height > 0.0
+            && self.floor_slope.abs() <= MAX_FLOOR_SLOPE;
+        if !box_ok {
+            return Err(LevelError::BadBox);
+        }
+
+        for (i, obstacle) in self.obstacles.iter().enumerate() {
+            let ok = match *obstacle {
+                Obstacle::Peg { at, radius } => at.iter().all(|v| v.is_finite()) && radius > 0.0,
+                Obstacle::Bar {
+                    from,
+                    to,
+                    thickness,
+                } => from.iter().chain(&to).all(|v| v.is_finite()) && from != to && thickness > 0.0,
+                Obstacle::Funnel { y, gap, depth } => {
+                    y.is_finite() && gap > 0.0 && gap < self.width && depth.is_finite()
+                }
+                Obstacle::Spinner { at, length, speed } => {
+                    at.iter().all(|v| v.is_finite()) && length > 0.0 && speed.is_finite()
+                }
+            };
+            if !ok {
+                return Err(LevelError::BadObstacle(i));
+            }
+        }
+
+        for (i, ball) in self.balls.iter().enumerate() {
+            let ok = ball.at.iter().all(|v| v.is_finite())
+                && ball.size < BALL_ORDER.len()
+                && ball.at[0].abs() < self.width / 2.0
+                && ball.at[1] >= 0.0;
+            if !ok {
+                return Err(LevelError::BadBall(i));
+            }
+        }
+
+        if self.queue.iter().any(|&size| size >= BALL_ORDER.len()) {
+            return Err(LevelError::BadQueue);
+        }
+        Ok(())
+    }
+
+    /// The size of the `n`th ball, if the queue decides it.
+    pub fn dealt(&self, n: usize) -> Option<usize> {
+        self.queue.get(n).copied()
+    }
+
+    /// How many balls are left to drop after `dropped`, or `None` if they never run out.
+    pub fn balls_left(&self, dropped: usize) -> Option<usize> {
+        if self.queue.is_empty() {
+            None
+        } else {
+            Some(self.queue.len().saturating_sub(dropped))
+        }
+    }
+
+    /// Height of the middle of the floor, in world space.
+    pub fn floor(&self) -> f32 {
+        // written so the classic box comes out exactly where it always has
+        TOP_OFFSET - BOX_HEIGHT / 2.0 - (self.height - BOX_HEIGHT)
+    }
+
+    /// Turns a position in the level, measured from the top of the middle of the floor, into
+    /// one in the world.
+    pub fn to_world(&self, at: [f32; 2]) -> Vec2 {
+        Vec2::new(at[0], self.floor() + WALL_THICKNESS / 2.0 + at[1])
+    }
+
+    /// Furthest a ball of `size` can be dropped from the centre without clipping a wall.
+    pub fn drop_limit(&self, params: &SimParams, size: usize) -> f32 {
+        self.width / 2.0 - params.radius(size) - WALL_THICKNESS / 2.0
+    }
+}
+
+/// Builds `level` in `world`: the box, then everything in it. Its balls aren't placed, as a
+/// restored run brings its own.
+pub fn add_level(world: &mut World, level: &Level) {
+    let half = level.width / 2.0;
+
+    // floor
+    if level.floor_slope == 0.0 {
+        world.spawn((
+            RigidBody::Static,
+            Collider::cuboid(level.width + WALL_THICKNESS, WALL_THICKNESS),
+            Position(Vec2::new(0.0, level.floor())),
+            SimEntity,
+        ));
+    } else {
+        // long enough to still meet both walls once it's tipped
+        let angle = level.floor_slope.to_radians();
+        let length = (level.width + WALL_THICKNESS) / angle.cos();
+        spawn_bar(
+            world,
+            RigidBody::Static,
+            Vec2::new(0.0, level.floor()),
+            angle,
+            length,
+            WALL_THICKNESS,
+        );
+    }
+
+    // left
+    world.spawn((
+        RigidBody::Static,
+        Collider::cuboid(WALL_THICKNESS, BOX_HEIGHT * 100.0), // walls are actually very tall, visually not
+        Position(Vec2::new(-half, TOP_OFFSET)),
+        SimEntity,
+    ));
+
+    // right
+    world.spawn((
+        RigidBody::Static,
+        Collider::cuboid(WALL_THICKNESS, BOX_HEIGHT * 100.0),
+        Position(Vec2::new(half, TOP_OFFSET)),
+        SimEntity,
+    ));
+
+    // roof (invisible, offscreen, saves from scammy explosion gameovers)
+    world.spawn((
+        RigidBody::Static,
+        Collider::cuboid(level.width + WALL_THICKNESS, WALL_THICKNESS),
+        Position(Vec2::new(0.0, 6.0)),
+        SimEntity,
+    ));
+
+    for obstacle in &level.obstacles {
+        match *obstacle {
+            Obstacle::Peg { at, radius } => {
+                let position = level.to_world(at);
+                world.spawn((
+                    RigidBody::Static,
+                    Collider::ball(radius),
+                    Position(position),
+                    TransformBundle::from_transform(Transform::from_translation(
+                        position.extend(0.0),
+                    )),
+                    Piece::Disc { radius },
+                    SimEntity,
+                ));
+            }
+            Obstacle::Bar {
+                from,
+                to,
+                thickness,
+            } => {
+                let (from, to) = (level.to_world(from), level.to_world(to));
+                let along = to - from;
+                spawn_bar(
+                    world,
+                    RigidBody::Static,
+                    (from + to) / 2.0,
+                    along.y.atan2(along.x),
+                    along.length(),
+                    thickness,
+                );
+            }
+            Obstacle::Funnel { y, gap, depth } => {
+                for side in [-1.0, 1.0] {
+                    let wall = level.to_world([side * half, y + depth]);
+                    let mouth = level.to_world([side * gap / 2.0, y]);
+                    let along = mouth - wall;
+                    spawn_bar(
+                        world,
+                        RigidBody::Static,
+                        (wall + mouth) / 2.0,
+                        along.y.atan2(along.x),
+                        along.length(),
+                        BAR_THICKNESS,
+                    );
+                }
+            }
+            Obstacle::Spinner { at, length, speed } => {
+                let entity = spawn_bar(
+                    world,
+                    RigidBody::Kinematic,
+                    level.to_world(at),
+                    0.0,
+                    length,
+                    BAR_THICKNESS,
+                );
+                world.entity_mut(entity).insert(AngularVelocity(speed));
+            }
+        }
+    }
+}
+
+fn spawn_bar(
+    world: &mut World,
+    body: RigidBody,
+    centre: Vec2,
+    angle: f32,
+    length: f32,
+    thickness: f32,
+) -> Entity {
+    world
+        .spawn((
+            body,
+            Collider::cuboid(length, thickness),
+            Position(centre),
+            Rotation::from_radians(angle),
+            TransformBundle::from_transform(
+                Transform::from_translation(centre.extend(0.0))
+                    .with_rotation(Quat::from_rotation_z(angle)),
+            ),
+            Piece::Bar { length, thickness },
+            SimEntity,
+        ))
+        .id()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        replay::{headless_app, simulate, step, Replay, ReplayOutcome},
+        sim::{reset_run, DropInput, NextBallTimer, RunConfig, RunOver, Score, SimTick},
+    };
+
+    #[test]
+    fn built_in_levels_load() {
+        for (name, source) in BUILT_IN {
+            assert_eq!(
+                Level::from_toml(source).map(|_| ()),
+                Ok(()),
+                "{} didn't load",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn an_empty_level_is_the_classic_box() {
+        assert_eq!(Level::from_toml(""), Ok(Level::default()));
+    }
+
+    #[test]
+    fn levels_that_cant_be_played_are_refused() {
+        assert_eq!(Level::from_toml("width = 1.0"), Err(LevelError::BadBox));
+        assert_eq!(
+            Level::from_toml("[[obstacles]]\nkind = \"peg\"\nat = [0.0, 1.0]\nradius = 0.0"),
+            Err(LevelError::BadObstacle(0))
+        );
+        assert_eq!(
+            Level::from_toml("[[balls]]\nat = [0.0, 1.0]\nsize = 11"),
+            Err(LevelError::BadBall(0))
+        );
+        assert_eq!(
+            Level::from_toml("queue = [0, 1, 99]"),
+            Err(LevelError::BadQueue)
+        );
+    }
+
+    #[test]
+    fn runs_on_other_levels_replay() {
+        for (name, _) in BUILT_IN {
+            let mut app = headless_app();
+            app.insert_resource(RunConfig {
+                seed: 9,
+                level: Level::built_in(name).unwrap(),
+                ..default()
+            });
+            reset_run(&mut app.world);
+
+            let mut dropped = 0;
+            while !app.world.resource::<RunOver>().0 && app.world.resource::<SimTick>().0 < 60 * 20
+            {
+                if app.world.resource::<NextBallTimer>().0.finished() {
+                    app.world.resource_mut::<DropInput>().0 =
+                        Some((dropped % 5) as f32 * 0.5 - 1.0);
+                    dropped += 1;
+                }
+                step(&mut app);
+            }
+
+            let outcome = ReplayOutcome {
+                score: app.world.resource::<Score>().0,
+                ticks: app.world.resource::<SimTick>().0,
+                game_over: app.world.resource::<RunOver>().0,
+            };
+            let mut replay = app.world.resource::<Replay>().clone();
+            if !outcome.game_over {
+                replay.end_tick = outcome.ticks;
+            }
+            assert_eq!(
+                simulate(&replay),
+                Ok(outcome),
+                "{} played out differently",
+                name
+            );
+        }
+    }
+}
@@ -0,0 +1,13 @@
+//! The parts of the game that don't need a window: the rules and physics, levels, challenges,
+//! replays, saved runs and the leaderboard protocol. The game binary and the leaderboard server
+//! both build on this.
+
+pub mod assets;
+pub mod bot;
+pub mod challenge;
+pub mod chaos;
+pub mod leaderboard;
+pub mod level;
+pub mod replay;
+pub mod save;
+pub mod sim;
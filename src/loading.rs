@@ -0,0 +1,107 @@
+//! Holds the game on a progress bar until everything in `setup` has actually arrived, so the
+//! menus never show up without their font or balls without their sprites.
+
+use bevy::{asset::LoadState, prelude::*};
+
+use crate::{layout::SafeArea, GameState};
+
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Loading), build_loading)
+            .add_systems(Update, track_loading.run_if(in_state(GameState::Loading)))
+            .add_systems(
+                OnExit(GameState::Loading),
+                crate::despawn_with::<LoadingTag>,
+            );
+    }
+}
+
+/// Everything the game waits for before showing the menu.
+#[derive(Resource)]
+pub struct LoadingAssets(pub Vec<HandleUntyped>);
+
+#[derive(Component)]
+struct LoadingTag;
+
+#[derive(Component)]
+struct ProgressBar;
+
+fn build_loading(mut commands: Commands) {
+    // the custom font is one of the things being waited for, so this sticks to the built-in one
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgb_u8(52, 52, 52).into(),
+                ..default()
+            },
+            LoadingTag,
+            SafeArea::default(),
+        ))
+        .with_children(|root| {
+            root.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Percent(60.0),
+                    height: Val::Px(24.0),
+                    border: UiRect::all(Val::Px(3.0)),
+                    ..default()
+                },
+                border_color: Color::ORANGE.into(),
+                ..default()
+            })
+            .with_children(|bar| {
+                bar.spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Percent(0.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        background_color: Color::ORANGE.into(),
+                        ..default()
+                    },
+                    ProgressBar,
+                ));
+            });
+        });
+}
+
+fn track_loading(
+    assets: Res<LoadingAssets>,
+    asset_server: Res<AssetServer>,
+    mut bar_q: Query<&mut Style, With<ProgressBar>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut commands: Commands,
+) {
+    let (mut loaded, mut failed) = (0, 0);
+    for handle in assets.0.iter() {
+        match asset_server.get_load_state(handle.id()) {
+            LoadState::Loaded => loaded += 1,
+            LoadState::Failed => failed += 1,
+            _ => {}
+        }
+    }
+    let done = loaded + failed;
+
+    if let Ok(mut style) = bar_q.get_single_mut() {
+        style.width = Val::Percent(100.0 * done as f32 / assets.0.len().max(1) as f32);
+    }
+
+    if done == assets.0.len() {
+        // better to play with something missing than to sit on this screen forever
+        if failed > 0 {
+            warn!("{} assets failed to load", failed);
+        }
+        next_state.0 = Some(GameState::Splash);
+        // let go, so assets that get swapped out later (like ball atlases) can be unloaded
+        commands.remove_resource::<LoadingAssets>();
+    }
+}
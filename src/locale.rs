@@ -0,0 +1,189 @@
+//! Every piece of text the player sees, in their language.
+//!
+//! Translations live in `locales/`, one file per language in a small subset of Fluent: one
+//! `key = value` message per line, with `{ $name }` placeables. They're compiled in, so there's
+//! nothing to wait for. The language comes from the system or browser locale until the player
+//! picks one with the language button on the splash screen.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use pumpkin_game::{assets, sim::BALL_ORDER};
+
+use crate::{storage, CustomFont};
+
+const STORAGE_KEY: &str = "language";
+
+pub struct Language {
+    pub code: &'static str,
+    source: &'static str,
+    /// Whether Creepster has glyphs for everything this language writes. Those it doesn't are
+    /// shown in Bevy's built-in font instead, which will need swapping for one that covers the
+    /// script before adding something like Japanese.
+    display_font: bool,
+}
+
+/// English first, as what every other language falls back to.
+const LANGUAGES: &[Language] = &[
+    Language {
+        code: "en",
+        source: include_str!("../locales/en.ftl"),
+        display_font: true,
+    },
+    Language {
+        code: "es",
+        source: include_str!("../locales/es.ftl"),
+        display_font: true,
+    },
+    Language {
+        code: "fr",
+        source: include_str!("../locales/fr.ftl"),
+        display_font: true,
+    },
+    Language {
+        code: "de",
+        source: include_str!("../locales/de.ftl"),
+        display_font: true,
+    },
+];
+
+pub struct LocalePlugin;
+
+impl Plugin for LocalePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Locale::new(detect_language()))
+            .add_systems(Update, (language_button, pick_font, localize_text).chain());
+    }
+}
+
+#[derive(Resource)]
+pub struct Locale {
+    language: usize,
+    messages: HashMap<&'static str, &'static str>,
+    fallback: HashMap<&'static str, &'static str>,
+}
+
+impl Locale {
+    fn new(language: usize) -> Self {
+        Locale {
+            language,
+            messages: parse(LANGUAGES[language].source),
+            fallback: parse(LANGUAGES[0].source),
+        }
+    }
+
+    pub fn language(&self) -> &'static Language {
+        &LANGUAGES[self.language]
+    }
+
+    pub fn get(&self, key: &str) -> String {
+        self.fmt(key, &[])
+    }
+
+    /// The message for `key` with its placeables filled in from `args`. Anything missing from
+    /// this language comes from English, and anything missing from that shows as the key.
+    pub fn fmt(&self, key: &str, args: &[(&str, String)]) -> String {
+        let template = self
+            .messages
+            .get(key)
+            .or_else(|| self.fallback.get(key))
+            .copied()
+            .unwrap_or(key);
+
+        let mut out = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let Some(end) = rest.find('}') else {
+                break;
+            };
+            let name = rest[1..end].trim().trim_start_matches('$');
+            match args.iter().find(|(arg, _)| *arg == name) {
+                Some((_, value)) => out.push_str(value),
+                None => out.push_str(&rest[..=end]),
+            }
+            rest = &rest[end + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// What a ball of `size` is called.
+    pub fn ball(&self, size: usize) -> String {
+        self.get(&format!("ball-{}", BALL_ORDER[size]))
+    }
+}
+
+fn parse(source: &'static str) -> HashMap<&'static str, &'static str> {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .collect()
+}
+
+/// The language picked last time, or failing that the closest to the system's.
+fn detect_language() -> usize {
+    let find = |code: &str| {
+        LANGUAGES.iter().position(|language| {
+            // "de-AT", "de_DE.UTF-8" and the like all get German
+            code.get(..2)
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(language.code))
+        })
+    };
+
+    storage::load::<String>(STORAGE_KEY)
+        .and_then(|code| find(&code))
+        .or_else(|| find(&sys_locale::get_locale()?))
+        .unwrap_or(0)
+}
+
+/// A piece of fixed text, kept in the current language.
+#[derive(Component)]
+pub struct Localized(pub &'static str);
+
+/// Cycles through the languages. Its label is the current language's own name for itself.
+#[derive(Component)]
+pub struct LanguageButton;
+
+fn language_button(
+    button_q: Query<&Interaction, (Changed<Interaction>, With<LanguageButton>)>,
+    mut locale: ResMut<Locale>,
+) {
+    if !button_q.iter().any(|i| *i == Interaction::Pressed) {
+        return;
+    }
+
+    *locale = Locale::new((locale.language + 1) % LANGUAGES.len());
+    storage::save(STORAGE_KEY, &locale.language().code);
+}
+
+fn pick_font(locale: Res<Locale>, mut font: ResMut<CustomFont>, asset_server: Res<AssetServer>) {
+    if !locale.is_changed() {
+        return;
+    }
+
+    font.0 = if locale.language().display_font {
+        asset_server.load(assets::FONT)
+    } else {
+        Handle::default()
+    };
+}
+
+fn localize_text(
+    mut text_q: Query<(&Localized, &mut Text)>,
+    locale: Res<Locale>,
+    font: Res<CustomFont>,
+) {
+    if !locale.is_changed() {
+        return;
+    }
+
+    for (localized, mut text) in text_q.iter_mut() {
+        text.sections[0].value = locale.get(localized.0);
+        text.sections[0].style.font = font.0.clone_weak();
+    }
+}
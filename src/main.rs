@@ -1,46 +1,49 @@
-use std::f32::{consts::PI, INFINITY};
-
 use bevy::{
-    audio::{PlaybackMode, Volume, VolumeLevel},
+    asset::LoadState,
     input::touch::TouchPhase,
     prelude::*,
-    sprite::MaterialMesh2dBundle,
-    window::WindowResized,
+    render::view::VisibilityBundle,
+    window::{WindowFocused, WindowResized},
 };
 use bevy_xpbd_2d::prelude::*;
-
-const DROP_LINE: f32 = 3.0;
-
-const BOX_WIDTH: f32 = 4.4;
-const BOX_HEIGHT: f32 = 5.0;
-const TOP_OFFSET: f32 = -0.8;
-
-const DEATH_LINE: f32 = 2.0;
-const OVERTOP_TIMER: f32 = 1.5;
-
-const MAX_RADIUS: f32 = (BOX_WIDTH / 2.2) / 2.0;
-const MIN_RADIUS: f32 = 0.15;
-
-const LINEAR_DAMPING: f32 = 3.0;
-const ANGULAR_DAMPING: f32 = 0.7;
-const FRICTION: f32 = 0.7;
-const RESTITUTION: f32 = 0.5;
-
-const G: f32 = 70.0;
-
-const BALL_ORDER: &'static [&'static str] = &[
-    "sweet",
-    "spider",
-    "bat",
-    "apple",
-    "candy_apple",
-    "ghost",
-    "vampire",
-    "mummy",
-    "frankenstein",
-    "skull",
-    "pumpkin",
-];
+use layout::{Hud, SafeArea};
+use locale::{Locale, Localized};
+use pumpkin_game::{
+    assets::{self, ATLAS_COLUMNS},
+    bot::{BoardBall, BoardState, DropStrategy, GreedyStrategy},
+    level::Level,
+    replay::Replay,
+    sim::{
+        ball_radius, end_run, lerp, reset_run, BallDroppedEvent, BallSize, BallsMergedEvent,
+        DropInput, GameOverEvent, NextBallSize, NextBallTimer, NextNextBallSize, RunConfig,
+        RunOver, Score, SimEntity, SimParams, SimPlugin, SimTick, BALL_ORDER, DROP_LINE,
+    },
+};
+use quality::{Quality, START_QUALITY};
+use sound::PlaySound;
+
+mod accessibility;
+mod achievements;
+mod challenge_ui;
+mod chaos_ui;
+mod combo;
+mod dev;
+mod evolution;
+mod help;
+mod impact;
+mod layout;
+mod loading;
+mod locale;
+mod music;
+mod online;
+mod quality;
+mod resume;
+mod scenery;
+mod sound;
+mod stats;
+mod storage;
+mod tutorial;
+mod tween;
 
 fn main() {
     App::new()
@@ -54,67 +57,96 @@ fn main() {
                     }),
                     ..default()
                 }),
-            PhysicsPlugins::default(),
+            SimPlugin,
+            loading::LoadingPlugin,
+            locale::LocalePlugin,
+            layout::LayoutPlugin,
+            scenery::SceneryPlugin,
+            online::OnlinePlugin,
+            challenge_ui::ChallengeUiPlugin,
+            chaos_ui::ChaosUiPlugin,
+        ))
+        .add_plugins((
+            achievements::AchievementsPlugin,
+            accessibility::AccessibilityPlugin,
+            stats::StatsPlugin,
+            evolution::EvolutionPlugin,
+            help::HelpPlugin,
+            tutorial::TutorialPlugin,
+            combo::ComboPlugin,
+            music::MusicPlugin,
+            sound::SoundPlugin,
+            impact::ImpactPlugin,
+            tween::TweenPlugin,
+            resume::ResumePlugin,
+            quality::QualityPlugin,
+            dev::DevPlugin,
         ))
-        .insert_resource(Gravity(Vec2::NEG_Y * G))
         .add_systems(Startup, setup)
         .add_systems(
             Update,
             (
                 (
-                    set_ball_sizes,
+                    fetch_ball_atlas,
                     fake_ball_follow_mouse,
                     cursor_to_world,
                     release_ball,
                     tick_next_ball,
-                    merge_on_collision,
+                    dress_balls,
+                    hint_button,
+                    fade_hint,
+                    ball_sounds,
                     enter_splash,
                     update_score,
                     update_next_up,
-                    check_over_top,
+                    on_game_over,
                     enter_gameover,
-                    change_quality,
-                    adaptive_quality,
-                    spawn_ball,
                 )
                     .run_if(in_state(GameState::Running)),
                 enter_running.run_if(in_state(GameState::Splash)),
                 enter_running.run_if(in_state(GameState::GameOver)),
                 music_button,
                 sfx_button,
-                do_kill_me,
                 set_scale_from_window,
                 play_button,
                 tick_debounce,
-                get_framerate,
+                pause_on_blur,
+                swap_ball_atlas,
             ),
         )
         .add_systems(OnEnter(GameState::Splash), build_splash)
         .add_systems(OnExit(GameState::Splash), despawn_with::<SplashTag>)
         .add_systems(
             OnEnter(GameState::Running),
-            (build_running, add_walls, set_next_size),
+            (
+                new_run_config,
+                reset_run,
+                resume::restore_run,
+                build_running,
+            )
+                .chain(),
+        )
+        .add_systems(
+            OnExit(GameState::Running),
+            (
+                resume::suspend_run,
+                (
+                    despawn_with::<RunningTag>,
+                    despawn_with::<SimEntity>,
+                    stop_run,
+                ),
+            )
+                .chain(),
         )
-        .add_systems(OnExit(GameState::Running), despawn_with::<RunningTag>)
         .add_systems(OnEnter(GameState::GameOver), build_gameover)
         .add_systems(OnExit(GameState::GameOver), despawn_with::<GameOverTag>)
-        .add_systems(OnEnter(NextBallState::Pick), set_next_size)
         .add_systems(OnEnter(AppState::Running), resume)
         .add_systems(OnExit(AppState::Running), pause)
         .init_resource::<CursorWorldPos>()
-        .init_resource::<NextBallSize>()
-        .init_resource::<NextNextBallSize>()
-        .init_resource::<Score>()
-        .init_resource::<Multiplier>()
-        .init_resource::<BallSizes>()
-        .init_resource::<Framerate>()
-        .init_resource::<AdaptiveQualityTimer>()
-        .insert_resource(NextBallTimer(Timer::from_seconds(0.5, TimerMode::Once)))
+        .add_event::<HintUsedEvent>()
         .insert_resource(DebounceTimer(Timer::from_seconds(0.3, TimerMode::Once)))
         .add_state::<AppState>()
         .add_state::<GameState>()
-        .add_state::<NextBallState>()
-        .add_event::<SpawnBallEvent>()
         .run();
 }
 
@@ -128,9 +160,15 @@ enum AppState {
 #[derive(Debug, Clone, Eq, PartialEq, Hash, States, Default)]
 enum GameState {
     #[default]
+    Loading,
     Splash,
     Running,
     GameOver,
+    Achievements,
+    Stats,
+    Accessibility,
+    HowToPlay,
+    Challenges,
 }
 
 #[derive(Component)]
@@ -142,9 +180,6 @@ struct RunningTag;
 #[derive(Component)]
 struct GameOverTag;
 
-#[derive(Component)]
-struct UiRoot;
-
 fn enter_running(keys: Res<Input<KeyCode>>, mut next_state: ResMut<NextState<GameState>>) {
     if keys.just_pressed(KeyCode::Space) {
         next_state.0 = Some(GameState::Running)
@@ -157,26 +192,54 @@ fn enter_splash(keys: Res<Input<KeyCode>>, mut next_state: ResMut<NextState<Game
     }
 }
 
-fn enter_gameover(keys: Res<Input<KeyCode>>, mut next_state: ResMut<NextState<GameState>>) {
+fn enter_gameover(
+    keys: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut over: ResMut<RunOver>,
+    mut replay: ResMut<Replay>,
+    tick: Res<SimTick>,
+) {
     if keys.just_pressed(KeyCode::G) {
+        end_run(&mut over, &mut replay, &tick);
         next_state.0 = Some(GameState::GameOver)
     }
 }
 
-fn change_quality(keys: Res<Input<KeyCode>>, mut quality: ResMut<Quality>) {
-    if keys.just_pressed(KeyCode::Q) {
-        quality.0 = match quality.0 {
-            32 => 64,
-            64 => 128,
-            128 => 256,
-            256 => 512,
-            _ => 32,
-        }
+fn on_game_over(mut er: EventReader<GameOverEvent>, mut next_state: ResMut<NextState<GameState>>) {
+    if er.iter().next().is_some() {
+        next_state.0 = Some(GameState::GameOver)
     }
 }
 
-#[derive(Component)]
-struct MusicTag;
+fn new_run_config(mut config: ResMut<RunConfig>) {
+    config.seed = fastrand::u64(..);
+}
+
+fn stop_run(mut over: ResMut<RunOver>) {
+    over.0 = true;
+}
+
+/// Stops the clock while the window is in the background, e.g. when the tab is switched away.
+fn pause_on_blur(
+    mut focus_er: EventReader<WindowFocused>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if let Some(ev) = focus_er.iter().last() {
+        next_state.0 = Some(if ev.focused {
+            AppState::Running
+        } else {
+            AppState::Paused
+        });
+    }
+}
+
+fn pause(mut time: ResMut<Time>) {
+    time.pause();
+}
+
+fn resume(mut time: ResMut<Time>) {
+    time.unpause();
+}
 
 #[derive(Resource)]
 struct MusicToggle(bool);
@@ -184,18 +247,6 @@ struct MusicToggle(bool);
 #[derive(Resource)]
 struct SoundToggle(bool);
 
-#[derive(Resource)]
-struct BallSizes(Vec<(f32, Handle<Mesh>, Handle<ColorMaterial>)>);
-
-impl Default for BallSizes {
-    fn default() -> Self {
-        BallSizes(vec![
-            (0.0, Handle::default(), Handle::default(),);
-            BALL_ORDER.len()
-        ])
-    }
-}
-
 #[derive(Resource)]
 struct AudioHandles {
     drop: Handle<AudioSource>,
@@ -203,31 +254,12 @@ struct AudioHandles {
     game_over: Handle<AudioSource>,
 }
 
-#[derive(Component)]
-struct BallSize(usize);
-
 fn despawn_with<T: Component>(mut commands: Commands, q: Query<Entity, With<T>>) {
     for e in q.iter() {
         commands.entity(e).despawn_recursive();
     }
 }
 
-fn lerp(v0: f32, v1: f32, t: f32) -> f32 {
-    v0 + t * (v1 - v0)
-}
-
-//fn ease_in_cubic(t: f32) -> f32 {
-//    t * t * t
-//}
-//
-//fn ease_in_circ(t: f32) -> f32 {
-//    1.0 - (1.0 - t.powi(2)).sqrt()
-//}
-
-fn ease_in_sine(t: f32) -> f32 {
-    1.0 - ((t * PI) / 2.0).cos()
-}
-
 fn set_scale_from_window(
     mut ev: EventReader<WindowResized>,
     mut projection: Query<&mut OrthographicProjection>,
@@ -244,7 +276,12 @@ fn set_scale_from_window(
 #[derive(Component)]
 struct ScoreTag;
 
-fn build_splash(mut commands: Commands, font: Res<CustomFont>) {
+fn build_splash(
+    mut commands: Commands,
+    font: Res<CustomFont>,
+    locale: Res<Locale>,
+    saved: Res<resume::SavedGame>,
+) {
     commands
         .spawn((
             NodeBundle {
@@ -260,18 +297,25 @@ fn build_splash(mut commands: Commands, font: Res<CustomFont>) {
                 ..default()
             },
             SplashTag,
+            SafeArea::default(),
         ))
         .with_children(|root| {
-            root.spawn((TextBundle::from_sections([TextSection {
-                value: "Pumpkin Game!\n\n\n\n".into(),
-                style: TextStyle {
-                    font_size: 40.0,
-                    font: font.0.clone_weak(),
+            root.spawn((
+                TextBundle::from_section(
+                    locale.get("title"),
+                    TextStyle {
+                        font_size: 40.0,
+                        font: font.0.clone_weak(),
+                        ..default()
+                    },
+                )
+                .with_text_alignment(TextAlignment::Center)
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(150.0)),
                     ..default()
-                },
-            }])
-            .with_text_alignment(TextAlignment::Center)
-            .with_style(Style { ..default() }),));
+                }),
+                Localized("title"),
+            ));
 
             root.spawn(NodeBundle {
                 style: Style {
@@ -283,104 +327,153 @@ fn build_splash(mut commands: Commands, font: Res<CustomFont>) {
                 ..default()
             })
             .with_children(|button_box| {
-                button_box
-                    .spawn((
-                        ButtonBundle {
-                            background_color: Color::GREEN.into(),
-                            border_color: Color::DARK_GREEN.into(),
-                            style: Style {
-                                width: Val::Px(150.0),
-                                height: Val::Px(64.0),
-                                justify_content: JustifyContent::Center,
-                                align_items: AlignItems::Center,
-                                border: UiRect::all(Val::Px(5.0)),
-                                flex_basis: Val::Percent(100.0),
-                                max_width: Val::Px(150.0),
-                                margin: UiRect::all(Val::Px(15.0)),
-                                ..default()
-                            },
-
-                            ..default()
-                        },
-                        PlayButton,
-                    ))
-                    .with_children(|parent| {
-                        parent.spawn(TextBundle::from_section(
-                            "Play",
-                            TextStyle {
-                                font: font.0.clone_weak(),
-                                font_size: 30.0,
-                                color: Color::WHITE,
-                            },
-                        ));
-                    });
-
-                button_box
-                    .spawn((
-                        ButtonBundle {
-                            background_color: Color::BLUE.into(),
-                            border_color: Color::MIDNIGHT_BLUE.into(),
-                            style: Style {
-                                width: Val::Px(150.0),
-                                height: Val::Px(64.0),
-                                justify_content: JustifyContent::Center,
-                                align_items: AlignItems::Center,
-                                border: UiRect::all(Val::Px(5.0)),
-                                flex_basis: Val::Percent(100.0),
-                                max_width: Val::Px(150.0),
-                                margin: UiRect::all(Val::Px(15.0)),
-                                ..default()
-                            },
-
-                            ..default()
-                        },
-                        MusicButton,
-                    ))
-                    .with_children(|parent| {
-                        parent.spawn(TextBundle::from_section(
-                            "Music",
-                            TextStyle {
-                                font: font.0.clone_weak(),
-                                font_size: 30.0,
-                                color: Color::WHITE,
-                            },
-                        ));
-                    });
-                button_box
-                    .spawn((
-                        ButtonBundle {
-                            background_color: Color::BLUE.into(),
-                            border_color: Color::MIDNIGHT_BLUE.into(),
-                            style: Style {
-                                width: Val::Px(150.0),
-                                height: Val::Px(64.0),
-                                justify_content: JustifyContent::Center,
-                                align_items: AlignItems::Center,
-                                border: UiRect::all(Val::Px(5.0)),
-                                flex_basis: Val::Percent(100.0),
-                                max_width: Val::Px(150.0),
-                                margin: UiRect::all(Val::Px(15.0)),
-                                ..default()
-                            },
-
-                            ..default()
-                        },
-                        SfxButton,
-                    ))
-                    .with_children(|parent| {
-                        parent.spawn(TextBundle::from_section(
-                            "Sounds",
-                            TextStyle {
-                                font: font.0.clone_weak(),
-                                font_size: 30.0,
-                                color: Color::WHITE,
-                            },
-                        ));
-                    });
+                let play = (Color::GREEN, Color::DARK_GREEN);
+                let toggle = (Color::BLUE, Color::MIDNIGHT_BLUE);
+                if saved.run.is_some() {
+                    spawn_button(
+                        button_box,
+                        &font,
+                        &locale,
+                        "continue",
+                        play,
+                        150.0,
+                        resume::ContinueButton,
+                    );
+                }
+                spawn_button(button_box, &font, &locale, "play", play, 150.0, PlayButton);
+                spawn_button(
+                    button_box,
+                    &font,
+                    &locale,
+                    "challenges",
+                    toggle,
+                    240.0,
+                    challenge_ui::ChallengesButton,
+                );
+                spawn_button(
+                    button_box,
+                    &font,
+                    &locale,
+                    "chaos",
+                    toggle,
+                    150.0,
+                    chaos_ui::ChaosButton,
+                );
+                spawn_button(
+                    button_box,
+                    &font,
+                    &locale,
+                    "how-to-play",
+                    toggle,
+                    240.0,
+                    help::HelpButton,
+                );
+                spawn_button(
+                    button_box,
+                    &font,
+                    &locale,
+                    "tutorial",
+                    toggle,
+                    240.0,
+                    tutorial::TutorialButton,
+                );
+                spawn_button(
+                    button_box,
+                    &font,
+                    &locale,
+                    "music",
+                    toggle,
+                    150.0,
+                    MusicButton,
+                );
+                spawn_button(
+                    button_box, &font, &locale, "sounds", toggle, 150.0, SfxButton,
+                );
+                spawn_button(
+                    button_box,
+                    &font,
+                    &locale,
+                    "achievements",
+                    toggle,
+                    240.0,
+                    achievements::AchievementsButton,
+                );
+                spawn_button(
+                    button_box,
+                    &font,
+                    &locale,
+                    "stats",
+                    toggle,
+                    150.0,
+                    stats::StatsButton,
+                );
+                spawn_button(
+                    button_box,
+                    &font,
+                    &locale,
+                    "language-name",
+                    toggle,
+                    240.0,
+                    locale::LanguageButton,
+                );
+                spawn_button(
+                    button_box,
+                    &font,
+                    &locale,
+                    "a11y",
+                    toggle,
+                    240.0,
+                    accessibility::AccessibilityButton,
+                );
             });
         });
 }
 
+/// A menu button labelled with the message `label`, `(background, border)` coloured.
+fn spawn_button(
+    parent: &mut ChildBuilder,
+    font: &CustomFont,
+    locale: &Locale,
+    label: &'static str,
+    (background, border): (Color, Color),
+    width: f32,
+    tag: impl Component,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                background_color: background.into(),
+                border_color: border.into(),
+                style: Style {
+                    width: Val::Px(width),
+                    height: Val::Px(64.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    border: UiRect::all(Val::Px(5.0)),
+                    flex_basis: Val::Percent(100.0),
+                    max_width: Val::Px(width),
+                    margin: UiRect::all(Val::Px(15.0)),
+                    ..default()
+                },
+                ..default()
+            },
+            tag,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    locale.get(label),
+                    TextStyle {
+                        font: font.0.clone_weak(),
+                        font_size: 30.0,
+                        color: Color::WHITE,
+                    },
+                ),
+                Localized(label),
+            ));
+        });
+}
+
 #[derive(Component)]
 struct PlayButton;
 
@@ -408,15 +501,11 @@ fn tick_debounce(mut timer: ResMut<DebounceTimer>, time: Res<Time>) {
 fn music_button(
     button_q: Query<&Interaction, With<MusicButton>>,
     keys: Res<Input<KeyCode>>,
-    bgm_q: Query<&mut AudioSink, With<MusicTag>>,
     mut toggle: ResMut<MusicToggle>,
     mut debounce: ResMut<DebounceTimer>,
 ) {
     if keys.just_pressed(KeyCode::M) {
         toggle.0 = !toggle.0;
-        if let Ok(sink) = bgm_q.get_single() {
-            sink.toggle();
-        }
         return;
     }
     if let Ok(interaction) = button_q.get_single() {
@@ -425,9 +514,6 @@ fn music_button(
         if *interaction == Interaction::Pressed && debounce.0.finished() {
             debounce.0.reset();
             toggle.0 = !toggle.0;
-            if let Ok(sink) = bgm_q.get_single() {
-                sink.toggle();
-            }
         }
     }
 }
@@ -451,26 +537,12 @@ fn sfx_button(
 }
 
 fn build_running(
-    mut score: ResMut<Score>,
     mut commands: Commands,
     next_ball_size: Res<NextBallSize>,
     font: Res<CustomFont>,
-    bgm_q: Query<&AudioSink, With<MusicTag>>,
-    bgm_toggle: Res<MusicToggle>,
-    mut next_ball_timer: ResMut<NextBallTimer>,
-    ball_images: Res<BallImageHandles>,
-    quality: Res<Quality>,
+    locale: Res<Locale>,
+    ball_atlas: Res<BallAtlas>,
 ) {
-    next_ball_timer.0.reset();
-
-    score.0 = 0;
-
-    if let Ok(sink) = bgm_q.get_single() {
-        if bgm_toggle.0 {
-            sink.play();
-        }
-    }
-
     let margin = UiRect {
         left: Val::Px(10.0),
         right: Val::Px(10.0),
@@ -491,11 +563,13 @@ fn build_running(
                 ..default()
             },
             RunningTag,
+            SafeArea::default(),
+            Hud,
         ))
         .with_children(|root| {
             root.spawn((
                 TextBundle::from_section(
-                    "Score: 0",
+                    locale.fmt("score", &[("score", "0".into())]),
                     TextStyle {
                         font_size: 30.0,
                         font: font.0.clone_weak(),
@@ -503,12 +577,42 @@ fn build_running(
                     },
                 )
                 .with_style(Style {
-                    margin: margin.clone(),
+                    margin,
                     ..default()
                 }),
                 ScoreTag,
             ));
 
+            combo::spawn_meter(root, &font);
+
+            root.spawn((
+                ButtonBundle {
+                    background_color: Color::PURPLE.into(),
+                    border_color: Color::INDIGO.into(),
+                    style: Style {
+                        width: Val::Px(100.0),
+                        height: Val::Px(48.0),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        border: UiRect::all(Val::Px(4.0)),
+                        margin,
+                        ..default()
+                    },
+                    ..default()
+                },
+                HintButton,
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    locale.get("hint"),
+                    TextStyle {
+                        font: font.0.clone_weak(),
+                        font_size: 30.0,
+                        color: Color::WHITE,
+                    },
+                ));
+            });
+
             root.spawn((NodeBundle {
                 style: Style {
                     width: Val::Px(50.0),
@@ -521,7 +625,7 @@ fn build_running(
             },))
                 .with_children(|next| {
                     next.spawn((TextBundle::from_section(
-                        "Next:",
+                        locale.get("next"),
                         TextStyle {
                             font_size: 30.0,
                             font: font.0.clone_weak(),
@@ -531,21 +635,34 @@ fn build_running(
                     .with_style(Style { ..default() }),));
 
                     next.spawn((
-                        NodeBundle {
-                            background_color: Color::WHITE.into(),
+                        AtlasImageBundle {
                             style: Style {
                                 width: Val::Px(50.0),
                                 height: Val::Px(50.0),
                                 ..default()
                             },
+                            texture_atlas: ball_atlas.0.clone_weak(),
+                            texture_atlas_image: UiTextureAtlasImage {
+                                index: next_ball_size.0,
+                                ..default()
+                            },
                             ..default()
                         },
-                        UiImage::new(
-                            ball_images.0[q_idx(quality.0)].0[next_ball_size.0].clone_weak(),
-                        ),
                         NextUpTag,
                     ));
                 });
+
+            root.spawn(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(0.0),
+                    bottom: Val::Px(10.0),
+                    width: Val::Percent(100.0),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|strip| evolution::spawn_strip(strip, &ball_atlas, 24.0));
         });
 }
 
@@ -553,46 +670,63 @@ fn build_running(
 struct NextUpTag;
 
 fn update_next_up(
-    next_q: Query<Entity, With<NextUpTag>>,
+    mut next_q: Query<(&mut UiTextureAtlasImage, &mut Visibility), With<NextUpTag>>,
     next_ball_size: Res<NextNextBallSize>,
-    mut commands: Commands,
-    ball_images: Res<BallImageHandles>,
-    quality: Res<Quality>,
+    replay: Res<Replay>,
+    level: Res<Level>,
 ) {
-    if !next_ball_size.is_changed() {
+    if !next_ball_size.is_changed() && !replay.is_changed() {
         return;
     }
 
-    if let Ok(entity) = next_q.get_single() {
-        commands.entity(entity).insert(UiImage::new(
-            ball_images.0[q_idx(quality.0)].0[next_ball_size.0].clone_weak(),
-        ));
+    if let Ok((mut image, mut visibility)) = next_q.get_single_mut() {
+        image.index = next_ball_size.0;
+        // a level's queue can run out before the ball after next
+        let coming = level
+            .balls_left(replay.drops.len())
+            .is_none_or(|left| left > 1);
+        *visibility = if coming {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
     }
 }
 
-fn update_score(score: Res<Score>, mut ui_q: Query<&mut Text, With<ScoreTag>>) {
+fn update_score(
+    score: Res<Score>,
+    locale: Res<Locale>,
+    mut ui_q: Query<&mut Text, With<ScoreTag>>,
+) {
     if !score.is_changed() {
         return;
     }
 
     if let Ok(mut text) = ui_q.get_single_mut() {
-        text.sections[0].value = format!("Score: {}", score.0);
+        text.sections[0].value = locale.fmt("score", &[("score", score.0.to_string())]);
     }
 }
 
 #[derive(Resource)]
 struct CustomFont(Handle<Font>);
 
+#[allow(clippy::too_many_arguments)]
 fn build_gameover(
     score: Res<Score>,
+    run_stats: Res<stats::RunStats>,
+    challenges: Res<challenge_ui::Challenges>,
     mut commands: Commands,
     font: Res<CustomFont>,
-    bgm_q: Query<&AudioSink, With<MusicTag>>,
+    locale: Res<Locale>,
     audio_handles: Res<AudioHandles>,
-    sound_toggle: Res<SoundToggle>,
+    mut sound_ew: EventWriter<PlaySound>,
 ) {
-    //
-    let score_string = format!("Score: {}", score.0);
+    let score_string = locale.fmt("score", &[("score", score.0.to_string())]);
+    let title = match challenges.result() {
+        None => "game-over",
+        Some(0) => "challenge-failed",
+        Some(_) => "challenge-complete",
+    };
 
     let style = TextStyle {
         font_size: 30.0,
@@ -601,13 +735,20 @@ fn build_gameover(
     };
     let gameover_text = (TextBundle::from_sections([
         TextSection {
-            value: "Skill Issue\n".into(),
+            value: format!("{}\n", locale.get(title)),
             style: style.clone(),
         },
         TextSection {
             value: score_string,
             style: style.clone(),
         },
+        TextSection {
+            value: format!("\n\n{}", run_stats.summary(&locale)),
+            style: TextStyle {
+                font_size: 20.0,
+                ..style.clone()
+            },
+        },
     ])
     .with_text_alignment(TextAlignment::Center),);
 
@@ -626,9 +767,13 @@ fn build_gameover(
                 ..default()
             },
             GameOverTag,
+            SafeArea::default(),
         ))
         .with_children(|root| {
             root.spawn(gameover_text);
+            if let Some(stars @ 1..) = challenges.result() {
+                challenge_ui::spawn_stars(root, stars, 30.0);
+            }
             root.spawn(NodeBundle {
                 style: Style {
                     width: Val::Percent(100.0),
@@ -662,7 +807,7 @@ fn build_gameover(
                     ))
                     .with_children(|parent| {
                         parent.spawn(TextBundle::from_section(
-                            "Play Again",
+                            locale.get("play-again"),
                             TextStyle {
                                 font: font.0.clone_weak(),
                                 font_size: 30.0,
@@ -673,150 +818,97 @@ fn build_gameover(
             });
         });
 
-    if let Ok(sink) = bgm_q.get_single() {
-        sink.pause();
-    }
-
-    if !sound_toggle.0 {
-        return;
-    }
-
-    commands
-        .spawn(AudioBundle {
-            source: audio_handles.game_over.clone_weak(),
-            settings: PlaybackSettings {
-                volume: Volume::Relative(VolumeLevel::new(0.7)),
-                ..default()
-            },
-        })
-        .insert(KillMeTimer(Timer::from_seconds(0.9, TimerMode::Once)));
-}
-
-#[derive(Resource)]
-struct Quality(usize);
-
-#[derive(Resource, Default)]
-struct Framerate(f32);
-
-const FRAME_SMOOTHING: f32 = 0.99;
-
-fn get_framerate(mut framerate: ResMut<Framerate>, time: Res<Time>) {
-    let mut current = 1.0 / time.delta().as_secs_f32();
-
-    if current == INFINITY {
-        current = 1.0;
-    }
-
-    framerate.0 = (framerate.0 * FRAME_SMOOTHING) + (current * (1.0 - FRAME_SMOOTHING));
-
-    //web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(
-    //    format!("CFR: {} | FR: {}", current, framerate.0).as_str(),
-    //));
+    sound_ew.send(PlaySound::new(&audio_handles.game_over).with_volume(0.7));
 }
 
-#[derive(Resource)]
-struct AdaptiveQualityTimer(Timer);
-
-impl Default for AdaptiveQualityTimer {
-    fn default() -> Self {
-        AdaptiveQualityTimer(Timer::from_seconds(5.0, TimerMode::Once))
-    }
-}
-
-fn adaptive_quality(
-    mut quality: ResMut<Quality>,
-    frames: Res<Framerate>,
-    mut debounce: ResMut<AdaptiveQualityTimer>,
-    time: Res<Time>,
+fn fetch_ball_atlas(
+    quality: Res<Quality>,
+    mut atlas_images: ResMut<BallAtlasImages>,
+    asset_server: Res<AssetServer>,
 ) {
-    debounce.0.tick(time.delta());
-
-    if !(debounce.0.finished()) {
+    if !quality.is_changed() {
         return;
     }
 
-    if frames.0 >= 59.0 && quality.0 < 512 {
-        quality.0 <<= 1;
-        debounce.0.reset();
-    }
-
-    if frames.0 <= 45.0 && quality.0 > 32 {
-        quality.0 >>= 1;
-        debounce.0.reset();
-    }
+    // only fetch the new level; swap_ball_atlas changes over once it's here
+    atlas_images.pending = (quality.0 != atlas_images.shown.0)
+        .then(|| (quality.0, asset_server.load(assets::ball_atlas(quality.0))));
 }
 
-fn set_ball_sizes(
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut ball_sizes: ResMut<BallSizes>,
-    quality: Res<Quality>,
-    mut ball_q: Query<(&BallSize, &mut Handle<Image>)>,
-    mut next_up_q: Query<&mut UiImage, With<NextUpTag>>,
-    next_up: Res<NextBallSize>,
-    ball_images: Res<BallImageHandles>,
+/// Changes the ball atlas over to a newly requested quality once its image has loaded. The old
+/// image is dropped, and unloaded along with it.
+fn swap_ball_atlas(
+    mut atlas_images: ResMut<BallAtlasImages>,
+    mut atlases: ResMut<Assets<TextureAtlas>>,
+    ball_atlas: Res<BallAtlas>,
+    asset_server: Res<AssetServer>,
 ) {
-    if !quality.is_changed() && !quality.is_added() {
+    let Some((quality, image)) = atlas_images.pending.clone() else {
         return;
-    }
-
-    for i in 1..=BALL_ORDER.len() {
-        let radius = lerp(
-            MIN_RADIUS,
-            MAX_RADIUS,
-            ease_in_sine(i as f32 / BALL_ORDER.len() as f32),
-        );
-        let mesh = meshes.add(shape::Circle::new(radius).into());
-        let mat = materials.add(ColorMaterial::from(Color::rgba(
-            0.5,
-            0.5,
-            i as f32 / BALL_ORDER.len() as f32,
-            0.0, // DEBUG:  remove this line to see collider
-        )));
-
-        ball_sizes.0[i - 1] = (radius, mesh, mat);
-    }
-
-    for (size, mut img) in ball_q.iter_mut() {
-        *img = ball_images.0[q_idx(quality.0)].0[size.0].clone_weak();
-    }
+    };
 
-    for mut img in next_up_q.iter_mut() {
-        img.texture = ball_images.0[q_idx(quality.0)].0[next_up.0].clone_weak();
+    match asset_server.get_load_state(&image) {
+        LoadState::Loaded => {
+            // everything draws from the one atlas, so swapping its texture is all a switch takes
+            if let Some(atlas) = atlases.get_mut(&ball_atlas.0) {
+                *atlas = quality_atlas(image.clone(), quality);
+            }
+            atlas_images.shown = (quality, image);
+            atlas_images.pending = None;
+        }
+        LoadState::Failed => {
+            warn!(
+                "couldn't load {}, staying at {}",
+                assets::ball_atlas(quality),
+                atlas_images.shown.0
+            );
+            atlas_images.pending = None;
+        }
+        _ => {}
     }
 }
 
-// TODO: this on impl Quality
-fn q_idx(i: usize) -> usize {
-    (i.ilog2() - 5) as usize
+/// Every ball packed into one image for the quality on screen, and the one being fetched to
+/// replace it. Other qualities aren't loaded at all.
+#[derive(Resource)]
+struct BallAtlasImages {
+    shown: (usize, Handle<Image>),
+    pending: Option<(usize, Handle<Image>)>,
 }
 
-/// quality then size
-#[derive(Clone)]
-struct BallImageHandleList(Vec<Handle<Image>>);
-
-/// quality then size
+/// What every ball sprite draws from. Its texture is swapped for the current quality's.
 #[derive(Resource)]
-struct BallImageHandles(Vec<BallImageHandleList>);
-
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let mut image_handles = vec![BallImageHandleList(vec![Handle::default(); BALL_ORDER.len()]); 5];
+struct BallAtlas(Handle<TextureAtlas>);
+
+fn quality_atlas(image: Handle<Image>, quality: usize) -> TextureAtlas {
+    TextureAtlas::from_grid(
+        image,
+        Vec2::splat(quality as f32),
+        ATLAS_COLUMNS,
+        assets::atlas_rows(),
+        None,
+        None,
+    )
+}
 
-    for i in 5..=9 {
-        let quality = 2usize.pow(i);
-        let idx = i as usize - 5;
-        for j in 0..BALL_ORDER.len() {
-            let name = BALL_ORDER[j];
-            image_handles[idx].0[j] = asset_server.load(format!("{}@{}.png", name, quality));
-        }
-    }
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let atlas_image: Handle<Image> = asset_server.load(assets::ball_atlas(START_QUALITY));
+    let mut to_load = vec![atlas_image.clone_untyped()];
 
-    commands.insert_resource(BallImageHandles(image_handles));
+    commands.insert_resource(BallAtlas(
+        atlases.add(quality_atlas(atlas_image.clone(), START_QUALITY)),
+    ));
+    commands.insert_resource(BallAtlasImages {
+        shown: (START_QUALITY, atlas_image),
+        pending: None,
+    });
 
     commands.insert_resource(ClearColor(Color::rgb_u8(52, 52, 52)));
 
-    commands.insert_resource(Quality(512)); //start at max quality, drop as needed
-
     commands.spawn(Camera2dBundle {
         projection: OrthographicProjection {
             near: -1.0,
@@ -827,121 +919,63 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         ..default()
     });
 
-    commands.insert_resource(CustomFont(asset_server.load("Creepster-Regular.ttf")));
+    let font = CustomFont(asset_server.load(assets::FONT));
+    to_load.push(font.0.clone_untyped());
+    commands.insert_resource(font);
 
     // Audio
-    commands.insert_resource(AudioHandles {
-        merge: asset_server.load("pop-1.ogg"),
-        drop: asset_server.load("drop-1.ogg"),
-        game_over: asset_server.load("game-over.ogg"),
-    });
+    let audio_handles = AudioHandles {
+        merge: asset_server.load(assets::MERGE_SOUND),
+        drop: asset_server.load(assets::DROP_SOUND),
+        game_over: asset_server.load(assets::GAME_OVER_SOUND),
+    };
+    to_load.extend(
+        [
+            &audio_handles.merge,
+            &audio_handles.drop,
+            &audio_handles.game_over,
+        ]
+        .map(|handle| handle.clone_untyped()),
+    );
+    commands.insert_resource(audio_handles);
 
     commands.insert_resource(SoundToggle(true));
     commands.insert_resource(MusicToggle(true));
 
-    // BGM
-    commands
-        .spawn(AudioBundle {
-            source: asset_server.load("spook.ogg"),
-            settings: PlaybackSettings {
-                mode: PlaybackMode::Loop,
-                volume: Volume::Relative(VolumeLevel::new(0.7)),
-                ..default()
-            },
-            ..default()
-        })
-        .insert(MusicTag);
+    // BGM, started once everything's loaded
+    let music = music::MusicHandles::load(&asset_server);
+    to_load.extend(music.untyped());
+    commands.insert_resource(music);
 
-    commands.spawn(SpriteBundle {
-        sprite: Sprite {
-            custom_size: Some(Vec2::new(4.8, 7.2)),
-            ..default()
-        },
-        texture: asset_server.load("bg.png"),
-        transform: Transform::from_xyz(0.0, 0.0, -1.0),
-        ..default()
-    });
+    let background = asset_server.load(assets::BACKGROUND);
+    let foreground = asset_server.load(assets::FOREGROUND);
+    to_load.extend([&background, &foreground].map(|handle| handle.clone_untyped()));
+    commands.insert_resource(loading::LoadingAssets(to_load));
 
-    commands.spawn(SpriteBundle {
-        sprite: Sprite {
-            custom_size: Some(Vec2::new(4.8, 7.2)),
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(Vec2::new(4.8, 7.2)),
+                ..default()
+            },
+            texture: background,
+            transform: Transform::from_xyz(0.0, 0.0, -1.0),
             ..default()
         },
-        texture: asset_server.load("fg.png"),
-        transform: Transform::from_xyz(0.0, 0.0, 1.0),
-        ..default()
-    });
-}
-
-fn add_walls(mut commands: Commands) {
-    // TODO: seperate debugdraw fn instead of commented code
-    //
-    //let square_sprite = Sprite {
-    //    color: Color::rgb_u8(200, 200, 200),
-    //    custom_size: Some(Vec2::splat(1.0)),
-    //    ..default()
-    //};
-
-    let wall_thickness = 1.0;
-
-    // floor
-    commands.spawn((
-        RigidBody::Static,
-        Collider::cuboid(BOX_WIDTH + wall_thickness, wall_thickness),
-        Position(Vec2::new(0.0, TOP_OFFSET - BOX_HEIGHT / 2.0)),
-        //SpriteBundle {
-        //    sprite: square_sprite.clone(),
-        //    transform: Transform::from_scale(Vec3::new(
-        //        BOX_WIDTH + wall_thickness,
-        //        wall_thickness,
-        //        1.0,
-        //    )),
-        //    ..default()
-        //},
-        RunningTag,
+        scenery::BoxArt,
     ));
 
-    // left
     commands.spawn((
-        RigidBody::Static,
-        Collider::cuboid(wall_thickness, BOX_HEIGHT * 100.0), // walls are actually very tall, visually not
-        Position(Vec2::new(-BOX_WIDTH / 2.0, TOP_OFFSET)),
-        //SpriteBundle {
-        //    sprite: square_sprite.clone(),
-        //    transform: Transform::from_scale(Vec3::new(wall_thickness, BOX_HEIGHT, 1.0)),
-        //    ..default()
-        //},
-        RunningTag,
-    ));
-
-    // right
-    commands.spawn((
-        RigidBody::Static,
-        Collider::cuboid(wall_thickness, BOX_HEIGHT * 100.0),
-        Position(Vec2::new(BOX_WIDTH / 2.0, TOP_OFFSET)),
-        //SpriteBundle {
-        //    sprite: square_sprite.clone(),
-        //    transform: Transform::from_scale(Vec3::new(wall_thickness, BOX_HEIGHT, 1.0)),
-        //    ..default()
-        //},
-        RunningTag,
-    ));
-
-    // roof (invisible, offscreen, saves from scammy explosion gameovers)
-    commands.spawn((
-        RigidBody::Static,
-        Collider::cuboid(BOX_WIDTH + wall_thickness, wall_thickness),
-        Position(Vec2::new(0.0, 6.0)),
-        //SpriteBundle {
-        //    sprite: square_sprite.clone(),
-        //    transform: Transform::from_scale(Vec3::new(
-        //        BOX_WIDTH + wall_thickness,
-        //        wall_thickness,
-        //        1.0,
-        //    )),
-        //    ..default()
-        //},
-        RunningTag,
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(Vec2::new(4.8, 7.2)),
+                ..default()
+            },
+            texture: foreground,
+            transform: Transform::from_xyz(0.0, 0.0, 1.0),
+            ..default()
+        },
+        scenery::BoxArt,
     ));
 }
 
@@ -951,32 +985,25 @@ struct FakeBall;
 fn fake_ball_follow_mouse(
     mut fake_ball_q: Query<(&mut Transform, &BallSize), With<FakeBall>>,
     cursor: Res<CursorWorldPos>,
-    ball_sizes: Res<BallSizes>,
+    params: Res<SimParams>,
+    level: Res<Level>,
 ) {
     if let Ok((mut transform, size)) = fake_ball_q.get_single_mut() {
-        let max = BOX_WIDTH / 2.0 - ball_sizes.0[size.0].0 - 0.5; // wall_thickness
-        let min = -BOX_WIDTH / 2.0 + ball_sizes.0[size.0].0 + 0.5;
-        transform.translation.x = cursor.0.x.clamp(min, max);
+        let limit = level.drop_limit(&params, size.0);
+        transform.translation.x = cursor.0.x.clamp(-limit, limit);
     }
 }
 
-#[derive(Resource)]
-struct NextBallTimer(Timer);
 #[derive(Resource)]
 struct DebounceTimer(Timer);
 
 fn release_ball(
-    mut next_ball_timer: ResMut<NextBallTimer>,
-    mut fake_ball_q: Query<(Entity, &Transform), With<FakeBall>>,
+    fake_ball_q: Query<(Entity, &Transform), With<FakeBall>>,
     mut commands: Commands,
     mouse: Res<Input<MouseButton>>,
     mut touch_evr: EventReader<TouchInput>,
-    mut next_ball_state: ResMut<NextState<NextBallState>>,
-    next_ball_size: Res<NextBallSize>,
-    mut multiplier: ResMut<Multiplier>,
-    audio_handles: Res<AudioHandles>,
-    sound_toggle: Res<SoundToggle>,
-    mut ew: EventWriter<SpawnBallEvent>,
+    mut drop_input: ResMut<DropInput>,
+    button_q: Query<&Interaction, With<Button>>,
 ) {
     let mut touch_ended = false;
 
@@ -993,95 +1020,185 @@ fn release_ball(
         return;
     }
 
-    multiplier.0 = 0;
-
-    next_ball_timer.0.reset();
+    // clicks on HUD buttons aren't drops
+    if button_q.iter().any(|i| *i != Interaction::None) {
+        return;
+    }
 
-    let size = next_ball_size.0;
+    if let Ok((entity, position)) = fake_ball_q.get_single() {
+        // the sim picks this up on its next step
+        drop_input.0 = Some(position.translation.x);
 
-    if let Ok((entity, position)) = fake_ball_q.get_single_mut() {
-        let av = -1.0 + fastrand::f32() * 2.0;
+        commands.entity(entity).despawn_recursive();
+    }
+}
 
-        ew.send(SpawnBallEvent {
-            position: position.translation.truncate(),
-            size,
-            av,
-        });
+#[derive(Component)]
+struct HintButton;
 
-        commands.entity(entity).despawn();
+#[derive(Event)]
+struct HintUsedEvent;
 
-        next_ball_state.0 = Some(NextBallState::Pick);
+#[derive(Component)]
+struct HintMarker(Timer);
 
-        if !sound_toggle.0 {
-            return;
-        }
+/// Shows where the greedy bot would drop the next ball. Lookahead plays better but is too slow
+/// to run on a button press on phones.
+#[allow(clippy::too_many_arguments)]
+fn hint_button(
+    button_q: Query<&Interaction, (Changed<Interaction>, With<HintButton>)>,
+    keys: Res<Input<KeyCode>>,
+    ball_q: Query<(&Position, &LinearVelocity, &BallSize), With<SimEntity>>,
+    marker_q: Query<Entity, With<HintMarker>>,
+    next_ball_size: Res<NextBallSize>,
+    next_next_ball_size: Res<NextNextBallSize>,
+    params: Res<SimParams>,
+    level: Res<Level>,
+    mut commands: Commands,
+    mut hint_ew: EventWriter<HintUsedEvent>,
+) {
+    if !keys.just_pressed(KeyCode::H) && !button_q.iter().any(|i| *i == Interaction::Pressed) {
+        return;
+    }
 
-        let speed = lerp(0.2, 1.2, 1.0 - (size as f32 / BALL_ORDER.len() as f32));
-        commands
-            .spawn(AudioBundle {
-                source: audio_handles.drop.clone_weak(),
-                settings: PlaybackSettings {
-                    volume: Volume::Relative(VolumeLevel::new(0.3)),
-                    speed,
-                    ..default()
-                },
-                ..default()
+    let board = BoardState {
+        balls: ball_q
+            .iter()
+            .map(|(pos, vel, size)| BoardBall {
+                position: pos.0,
+                velocity: vel.0,
+                size: size.0,
             })
-            .insert(KillMeTimer(Timer::from_seconds(0.5, TimerMode::Once)));
+            .collect(),
+        next: next_ball_size.0,
+        next_next: next_next_ball_size.0,
+        params: params.clone(),
+        level: level.clone(),
+    };
+    let x = GreedyStrategy.choose(&board);
+    hint_ew.send(HintUsedEvent);
+
+    for entity in marker_q.iter() {
+        commands.entity(entity).despawn();
     }
-}
 
-#[derive(Component)]
-struct KillMeTimer(Timer);
+    let height = DROP_LINE - level.floor();
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(1.0, 1.0, 1.0, 0.25),
+                custom_size: Some(Vec2::new(ball_radius(board.next) * 2.0, height)),
+                ..default()
+            },
+            transform: Transform::from_xyz(x, DROP_LINE - height / 2.0, 0.5),
+            ..default()
+        },
+        HintMarker(Timer::from_seconds(1.5, TimerMode::Once)),
+        RunningTag,
+    ));
+}
 
-fn do_kill_me(
+fn fade_hint(
+    mut marker_q: Query<(Entity, &mut HintMarker, &mut Sprite)>,
     mut commands: Commands,
-    mut audio_q: Query<(Entity, &mut KillMeTimer)>,
     time: Res<Time>,
 ) {
-    for (entity, mut timer) in audio_q.iter_mut() {
-        timer.0.tick(time.delta());
-        if !timer.0.finished() {
-            return;
+    for (entity, mut marker, mut sprite) in marker_q.iter_mut() {
+        marker.0.tick(time.delta());
+        sprite.color.set_a(0.25 * marker.0.percent_left());
+        if marker.0.finished() {
+            commands.entity(entity).despawn();
         }
+    }
+}
 
-        commands.entity(entity).despawn();
+/// Gives balls the sim spawned something to look at.
+fn dress_balls(
+    ball_q: Query<(Entity, &BallSize), Added<SimEntity>>,
+    mut commands: Commands,
+    ball_atlas: Res<BallAtlas>,
+    params: Res<SimParams>,
+) {
+    for (entity, size) in ball_q.iter() {
+        let radius = params.radius(size.0);
+
+        commands.entity(entity).insert((
+            ball_atlas.0.clone_weak(),
+            TextureAtlasSprite {
+                index: size.0,
+                custom_size: Some(Vec2::splat(radius * 2.0)),
+                ..default()
+            },
+            VisibilityBundle::default(),
+            RunningTag,
+        ));
     }
 }
 
+fn ball_sounds(
+    mut dropped_er: EventReader<BallDroppedEvent>,
+    mut merged_er: EventReader<BallsMergedEvent>,
+    mut sound_ew: EventWriter<PlaySound>,
+    audio_handles: Res<AudioHandles>,
+) {
+    for ev in dropped_er.iter() {
+        let speed = lerp(0.2, 1.2, 1.0 - (ev.size as f32 / BALL_ORDER.len() as f32));
+        sound_ew.send(
+            PlaySound::new(&audio_handles.drop)
+                .with_volume(0.3)
+                .with_speed(speed)
+                .at(ev.position.x),
+        );
+    }
+
+    for ev in merged_er.iter() {
+        let speed = lerp(0.2, 1.2, 1.0 - (ev.size as f32 / BALL_ORDER.len() as f32))
+            * combo::pitch(ev.multiplier);
+        sound_ew.send(
+            PlaySound::new(&audio_handles.merge)
+                .with_speed(speed)
+                .at(ev.position.x),
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn tick_next_ball(
-    mut next_ball_timer: ResMut<NextBallTimer>,
-    time: Res<Time>,
+    next_ball_timer: Res<NextBallTimer>,
+    drop_input: Res<DropInput>,
     mut commands: Commands,
-    ball_sizes: Res<BallSizes>,
+    params: Res<SimParams>,
     fake_ball_q: Query<&FakeBall>,
     next_ball_size: Res<NextBallSize>,
+    replay: Res<Replay>,
+    level: Res<Level>,
     cursor: Res<CursorWorldPos>,
-    ball_images: Res<BallImageHandles>,
-    quality: Res<Quality>,
+    ball_atlas: Res<BallAtlas>,
 ) {
-    if next_ball_timer.0.finished() && fake_ball_q.is_empty() {
-        let radius = ball_sizes.0[next_ball_size.0].0;
+    if level.balls_left(replay.drops.len()) == Some(0) {
+        return;
+    }
+
+    // wait for the sim to take the last drop before offering another
+    if next_ball_timer.0.finished() && fake_ball_q.is_empty() && drop_input.0.is_none() {
+        let radius = params.radius(next_ball_size.0);
 
         commands.spawn((
             FakeBall,
             BallSize(next_ball_size.0),
             RunningTag,
-            SpriteBundle {
-                sprite: Sprite {
+            SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    index: next_ball_size.0,
                     custom_size: Some(Vec2::splat(radius * 2.0)),
                     ..default()
                 },
+                texture_atlas: ball_atlas.0.clone_weak(),
                 transform: Transform::from_xyz(cursor.0.x, DROP_LINE, 0.0),
-                texture: ball_images.0[q_idx(quality.0)].0[next_ball_size.0].clone_weak(),
                 ..default()
             },
         ));
-
-        return;
     }
-
-    next_ball_timer.0.tick(time.delta());
 }
 
 #[derive(Resource, Default)]
@@ -1121,169 +1238,3 @@ fn cursor_to_world(
         }
     }
 }
-
-#[derive(Resource, Default)]
-struct Multiplier(usize);
-
-fn merge_on_collision(
-    mut collision_event_reader: EventReader<Collision>,
-    ballsize_q: Query<(&BallSize, &Position, &LinearVelocity, &AngularVelocity)>,
-    mut commands: Commands,
-    ball_sizes: Res<BallSizes>,
-    mut score: ResMut<Score>,
-    mut multiplier: ResMut<Multiplier>,
-    audio_handles: Res<AudioHandles>,
-    sound_toggle: Res<SoundToggle>,
-    mut ew: EventWriter<SpawnBallEvent>,
-) {
-    for Collision(contact) in collision_event_reader.iter() {
-        // Check BallSize component on entities. If present and equal, remove the two contacting
-        // entities and spawn a ball with the next size at the midpoint of the contacting ball's
-        // positions.
-        let entity1 = contact.entity1;
-        let entity2 = contact.entity2;
-
-        if let Ok((ball1, pos1, lv1, av1)) = ballsize_q.get(entity1) {
-            if let Ok((ball2, pos2, lv2, av2)) = ballsize_q.get(entity2) {
-                if ball1.0 == ball2.0 {
-                    let size = ball1.0 + 1;
-
-                    if size >= ball_sizes.0.len() {
-                        continue;
-                    }
-
-                    multiplier.0 += 1;
-
-                    score.0 += size * multiplier.0;
-
-                    // Magic numbers to stop insane velocities
-                    let _lv = (lv1.0 + lv2.0) / 10.0;
-                    let av = (av1.0 + av2.0) / 4.0;
-
-                    //println!("AV {:?}, LV {:?}, POS {:?}", av, lv, position);
-
-                    let position = (pos1.0 + pos2.0) / 2.0;
-
-                    ew.send(SpawnBallEvent { position, size, av });
-
-                    commands.entity(entity1).despawn();
-                    commands.entity(entity2).despawn();
-
-                    if !sound_toggle.0 {
-                        return;
-                    }
-                    let speed = lerp(0.2, 1.2, 1.0 - (size as f32 / BALL_ORDER.len() as f32));
-                    commands
-                        .spawn(AudioBundle {
-                            source: audio_handles.merge.clone_weak(),
-                            settings: PlaybackSettings { speed, ..default() },
-                            ..default()
-                        })
-                        .insert(KillMeTimer(Timer::from_seconds(0.5, TimerMode::Once)));
-                    // one merge per frame to prevent doubling stuffs
-                    return;
-                }
-            }
-        }
-    }
-}
-
-#[derive(Event)]
-struct SpawnBallEvent {
-    position: Vec2,
-    size: usize,
-    av: f32,
-}
-
-fn spawn_ball(
-    mut er: EventReader<SpawnBallEvent>,
-    ball_images: Res<BallImageHandles>,
-    ball_sizes: Res<BallSizes>,
-    quality: Res<Quality>,
-    mut commands: Commands,
-) {
-    for ev in er.iter() {
-        let radius = ball_sizes.0[ev.size].0;
-
-        let matmesh = MaterialMesh2dBundle {
-            mesh: ball_sizes.0[ev.size].1.clone_weak().into(),
-            material: ball_sizes.0[ev.size].2.clone_weak(),
-            ..default()
-        };
-
-        commands.spawn((
-            RigidBody::Dynamic,
-            Collider::ball(radius),
-            matmesh,
-            Position(ev.position),
-            LinearDamping(LINEAR_DAMPING),
-            AngularDamping(ANGULAR_DAMPING),
-            BallSize(ev.size),
-            Friction::new(FRICTION),
-            RunningTag,
-            SettleTimer(Timer::from_seconds(OVERTOP_TIMER, TimerMode::Once)),
-            Restitution::new(RESTITUTION),
-            ball_images.0[q_idx(quality.0)].0[ev.size].clone_weak(),
-            AngularVelocity(ev.av),
-            Sprite {
-                custom_size: Some(Vec2::splat(radius * 2.0)),
-                ..default()
-            },
-        ));
-    }
-}
-
-#[derive(Resource, Default)]
-struct NextBallSize(usize);
-
-#[derive(Resource)]
-struct NextNextBallSize(usize);
-
-impl Default for NextNextBallSize {
-    fn default() -> Self {
-        Self(fastrand::usize(..3))
-    }
-}
-
-#[derive(Debug, Clone, Eq, PartialEq, Hash, States, Default)]
-enum NextBallState {
-    Pick,
-    #[default]
-    Selected,
-}
-
-fn set_next_size(
-    mut next_size: ResMut<NextBallSize>,
-    mut next_next_size: ResMut<NextNextBallSize>,
-    mut next_state: ResMut<NextState<NextBallState>>,
-) {
-    let x: usize = fastrand::usize(..BALL_ORDER.len() / 2); //rng.gen_range(0..SIZE_COUNT / 2);
-    next_size.0 = next_next_size.0;
-    next_next_size.0 = x;
-    next_state.0 = Some(NextBallState::Selected);
-}
-
-#[derive(Resource, Default)]
-struct Score(usize);
-
-#[derive(Component)]
-struct SettleTimer(Timer);
-
-fn check_over_top(
-    mut ball_q: Query<(&Position, &mut SettleTimer, &BallSize), Without<FakeBall>>,
-    ball_sizes: Res<BallSizes>,
-    time: Res<Time>,
-    mut next_state: ResMut<NextState<GameState>>,
-) {
-    for (pos, mut timer, size) in ball_q.iter_mut() {
-        let ball_top = pos.y + ball_sizes.0[size.0].0;
-        if ball_top > DEATH_LINE {
-            timer.0.tick(time.delta());
-            if timer.0.finished() {
-                next_state.0 = Some(GameState::GameOver);
-            }
-        } else {
-            timer.0.reset();
-        }
-    }
-}
@@ -0,0 +1,209 @@
+//! Music in layers that follow the game: the tune alone on the menus, a tense layer over it as the
+//! pile creeps up on the death line, a sting for each new biggest ball, and quiet for the game
+//! over jingle. Moving between screens fades the layers rather than cutting them.
+
+use bevy::{
+    audio::{PlaybackMode, Volume, VolumeLevel},
+    prelude::*,
+};
+use bevy_xpbd_2d::prelude::*;
+use pumpkin_game::{
+    assets,
+    sim::{BallSize, BallsMergedEvent, SimEntity, SimParams, DEATH_LINE},
+};
+
+use crate::{GameState, MusicToggle};
+
+/// How long a layer takes to fade all the way in or out.
+const FADE_SECS: f32 = 0.8;
+const MENU_VOLUME: f32 = 0.4;
+const RUN_VOLUME: f32 = 0.7;
+const TENSE_VOLUME: f32 = 0.8;
+/// How far below the death line the top of the pile starts making the music tense.
+const DANGER_RANGE: f32 = 1.5;
+/// Balls moving faster than this are still falling into place, so don't count as the pile.
+const SETTLED_SPEED: f32 = 1.0;
+/// Only new biggest balls from this size up get a sting, so early merges don't set it off.
+const STING_FROM: usize = 4;
+const STING_SECS: f32 = 1.6;
+/// What the layers are turned down to while a sting plays.
+const STING_DUCK: f32 = 0.5;
+
+pub struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Mix>()
+            .add_systems(OnExit(GameState::Loading), start_layers)
+            .add_systems(OnEnter(GameState::Running), reset_mix)
+            .add_systems(
+                Update,
+                (measure_danger, play_sting).run_if(in_state(GameState::Running)),
+            )
+            .add_systems(Update, mix_layers);
+    }
+}
+
+#[derive(Resource)]
+pub struct MusicHandles {
+    calm: Handle<AudioSource>,
+    tense: Handle<AudioSource>,
+    sting: Handle<AudioSource>,
+}
+
+impl MusicHandles {
+    pub fn load(asset_server: &AssetServer) -> Self {
+        MusicHandles {
+            calm: asset_server.load(assets::MUSIC),
+            tense: asset_server.load(assets::MUSIC_TENSE),
+            sting: asset_server.load(assets::STING_SOUND),
+        }
+    }
+
+    pub fn untyped(&self) -> [HandleUntyped; 3] {
+        [&self.calm, &self.tense, &self.sting].map(|handle| handle.clone_untyped())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Layer {
+    Calm,
+    Tense,
+}
+
+#[derive(Component)]
+struct Stem {
+    layer: Layer,
+    /// Where its fade is up to.
+    volume: f32,
+}
+
+#[derive(Resource)]
+struct Mix {
+    /// How close the pile is to the death line, from nothing at 0 to touching it at 1.
+    danger: f32,
+    /// The biggest ball this run, once the board has been looked at.
+    highest: Option<usize>,
+    sting: Timer,
+}
+
+impl Default for Mix {
+    fn default() -> Self {
+        let mut sting = Timer::from_seconds(STING_SECS, TimerMode::Once);
+        sting.tick(sting.duration());
+        Mix {
+            danger: 0.0,
+            highest: None,
+            sting,
+        }
+    }
+}
+
+/// Starts every layer at once, silent, so they loop in time with each other. Everything is
+/// loaded by now, so none of them starts late.
+fn start_layers(mut commands: Commands, handles: Res<MusicHandles>) {
+    for (layer, source) in [(Layer::Calm, &handles.calm), (Layer::Tense, &handles.tense)] {
+        commands.spawn((
+            AudioBundle {
+                source: source.clone(),
+                settings: PlaybackSettings {
+                    mode: PlaybackMode::Loop,
+                    volume: Volume::Relative(VolumeLevel::new(0.0)),
+                    ..default()
+                },
+            },
+            Stem { layer, volume: 0.0 },
+        ));
+    }
+}
+
+fn reset_mix(mut mix: ResMut<Mix>) {
+    *mix = Mix::default();
+}
+
+fn measure_danger(
+    ball_q: Query<(&Position, &LinearVelocity, &BallSize), With<SimEntity>>,
+    params: Res<SimParams>,
+    mut mix: ResMut<Mix>,
+) {
+    let top = ball_q
+        .iter()
+        .filter(|(_, velocity, _)| velocity.0.length() < SETTLED_SPEED)
+        .map(|(position, _, size)| position.y + params.radius(size.0))
+        .fold(f32::MIN, f32::max);
+
+    mix.danger = ((top - (DEATH_LINE - DANGER_RANGE)) / DANGER_RANGE).clamp(0.0, 1.0);
+}
+
+fn play_sting(
+    mut merged_er: EventReader<BallsMergedEvent>,
+    ball_q: Query<&BallSize, With<SimEntity>>,
+    mut mix: ResMut<Mix>,
+    mut commands: Commands,
+    handles: Res<MusicHandles>,
+    toggle: Res<MusicToggle>,
+    time: Res<Time>,
+) {
+    mix.sting.tick(time.delta());
+
+    // a continued run starts from what's already on the board
+    let highest = *mix
+        .highest
+        .get_or_insert_with(|| ball_q.iter().map(|size| size.0).max().unwrap_or(0));
+    let Some(biggest) = merged_er.iter().map(|ev| ev.size).max() else {
+        return;
+    };
+    if biggest <= highest {
+        return;
+    }
+    mix.highest = Some(biggest);
+
+    if biggest < STING_FROM || !toggle.0 {
+        return;
+    }
+    mix.sting.reset();
+    commands.spawn(AudioBundle {
+        source: handles.sting.clone_weak(),
+        settings: PlaybackSettings {
+            mode: PlaybackMode::Despawn,
+            volume: Volume::Relative(VolumeLevel::new(0.8)),
+            ..default()
+        },
+    });
+}
+
+fn mix_layers(
+    mut stem_q: Query<(&mut Stem, &AudioSink)>,
+    state: Res<State<GameState>>,
+    mix: Res<Mix>,
+    toggle: Res<MusicToggle>,
+    time: Res<Time>,
+) {
+    let (calm, tense) = match state.get() {
+        GameState::Running => (RUN_VOLUME, TENSE_VOLUME * mix.danger),
+        // the jingle has the game over screen to itself
+        GameState::Loading | GameState::GameOver => (0.0, 0.0),
+        _ => (MENU_VOLUME, 0.0),
+    };
+    let duck = if mix.sting.finished() {
+        1.0
+    } else {
+        STING_DUCK
+    };
+    let on = if toggle.0 { 1.0 } else { 0.0 };
+    let step = time.delta_seconds() / FADE_SECS;
+
+    for (mut stem, sink) in stem_q.iter_mut() {
+        let target = match stem.layer {
+            Layer::Calm => calm,
+            Layer::Tense => tense,
+        } * duck
+            * on;
+
+        let volume = stem.volume + (target - stem.volume).clamp(-step, step);
+        if volume != stem.volume {
+            stem.volume = volume;
+            sink.set_volume(volume);
+        }
+    }
+}
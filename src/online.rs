@@ -0,0 +1,257 @@
+//! Optional online leaderboard.
+//!
+//! Only shows up when the game knows where a leaderboard server lives, either from the
+//! `PUMPKIN_LEADERBOARD_URL` environment variable (native) or the same variable at build time.
+//! The player's name comes from `PUMPKIN_PLAYER_NAME` the same way. For local testing run
+//! `cargo run -p leaderboard-server` and point the game at `http://127.0.0.1:8787`.
+//!
+//! Submit is only offered for runs the leaderboard ranks, and that aren't too long to check.
+//! Runs changed from the dev console, or continued from a save, aren't offered either.
+
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use pumpkin_game::{
+    leaderboard::{self, Submission, SubmitResponse},
+    replay::{Replay, MAX_REPLAY_TICKS},
+    sim::Score,
+};
+
+use crate::{
+    dev::ConsoleUsed, layout::SafeArea, locale::Locale, resume::SavedGame, CustomFont, GameOverTag,
+    GameState,
+};
+
+pub struct OnlinePlugin;
+
+impl Plugin for OnlinePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LeaderboardConfig::from_env())
+            .init_resource::<SubmitStatus>()
+            .add_systems(OnEnter(GameState::GameOver), build_submit)
+            .add_systems(
+                Update,
+                (submit_button, update_submit_text).run_if(in_state(GameState::GameOver)),
+            );
+    }
+}
+
+#[derive(Resource)]
+struct LeaderboardConfig {
+    endpoint: Option<String>,
+    player_name: String,
+}
+
+impl LeaderboardConfig {
+    fn from_env() -> Self {
+        let var = |name: &str| {
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Ok(v) = std::env::var(name) {
+                return Some(v);
+            }
+            match name {
+                "PUMPKIN_LEADERBOARD_URL" => option_env!("PUMPKIN_LEADERBOARD_URL"),
+                "PUMPKIN_PLAYER_NAME" => option_env!("PUMPKIN_PLAYER_NAME"),
+                _ => None,
+            }
+            .map(String::from)
+        };
+
+        LeaderboardConfig {
+            endpoint: var("PUMPKIN_LEADERBOARD_URL")
+                .filter(|url| !url.is_empty())
+                .map(|url| url.trim_end_matches('/').to_string()),
+            player_name: var("PUMPKIN_PLAYER_NAME").unwrap_or_else(|| "Anonymous".into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+enum Status {
+    #[default]
+    Idle,
+    Sending,
+    Done(SubmitResponse),
+    Failed(String),
+}
+
+/// Written to from the HTTP callback, which may be on another thread.
+#[derive(Resource, Default)]
+struct SubmitStatus(Arc<Mutex<Status>>);
+
+#[derive(Component)]
+struct SubmitButton;
+
+#[derive(Component)]
+struct SubmitText;
+
+#[allow(clippy::too_many_arguments)]
+fn build_submit(
+    mut commands: Commands,
+    config: Res<LeaderboardConfig>,
+    replay: Res<Replay>,
+    console: Res<ConsoleUsed>,
+    saved: Res<SavedGame>,
+    font: Res<CustomFont>,
+    locale: Res<Locale>,
+    status: Res<SubmitStatus>,
+) {
+    if config.endpoint.is_none()
+        || !leaderboard::is_ranked(&replay)
+        || replay.end_tick > MAX_REPLAY_TICKS
+        || console.0
+        || saved.restored
+    {
+        return;
+    }
+
+    *status.0.lock().unwrap() = Status::Idle;
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(30.0),
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    flex_wrap: FlexWrap::Wrap,
+                    ..default()
+                },
+                ..default()
+            },
+            GameOverTag,
+            SafeArea::default(),
+        ))
+        .with_children(|root| {
+            root.spawn((
+                ButtonBundle {
+                    background_color: Color::ORANGE.into(),
+                    border_color: Color::ORANGE_RED.into(),
+                    style: Style {
+                        width: Val::Px(200.0),
+                        height: Val::Px(64.0),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        border: UiRect::all(Val::Px(5.0)),
+                        flex_basis: Val::Percent(100.0),
+                        max_width: Val::Px(200.0),
+                        margin: UiRect::all(Val::Px(15.0)),
+                        ..default()
+                    },
+                    ..default()
+                },
+                SubmitButton,
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    locale.get("submit-score"),
+                    TextStyle {
+                        font: font.0.clone_weak(),
+                        font_size: 30.0,
+                        color: Color::WHITE,
+                    },
+                ));
+            });
+
+            root.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.0.clone_weak(),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_text_alignment(TextAlignment::Center)
+                .with_style(Style {
+                    flex_basis: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                }),
+                SubmitText,
+            ));
+        });
+}
+
+fn submit_button(
+    button_q: Query<&Interaction, (Changed<Interaction>, With<SubmitButton>)>,
+    config: Res<LeaderboardConfig>,
+    status: Res<SubmitStatus>,
+    score: Res<Score>,
+    replay: Res<Replay>,
+    console: Res<ConsoleUsed>,
+    saved: Res<SavedGame>,
+) {
+    let Some(endpoint) = &config.endpoint else {
+        return;
+    };
+    if console.0 || saved.restored {
+        return;
+    }
+
+    if !button_q.iter().any(|i| *i == Interaction::Pressed) {
+        return;
+    }
+
+    {
+        let mut status = status.0.lock().unwrap();
+        if !matches!(*status, Status::Idle | Status::Failed(_)) {
+            return;
+        }
+        *status = Status::Sending;
+    }
+
+    let submission = Submission {
+        name: config.player_name.clone(),
+        score: score.0,
+        replay: replay.clone(),
+    };
+
+    let request = match ehttp::Request::json(format!("{}/scores", endpoint), &submission) {
+        Ok(request) => request,
+        Err(e) => {
+            *status.0.lock().unwrap() = Status::Failed(e.to_string());
+            return;
+        }
+    };
+
+    let status = status.0.clone();
+    ehttp::fetch(request, move |result| {
+        let new_status = match result {
+            Ok(response) if response.ok => match response.json::<SubmitResponse>() {
+                Ok(response) => Status::Done(response),
+                Err(e) => Status::Failed(e.to_string()),
+            },
+            Ok(response) => Status::Failed(format!("{} {}", response.status, response.status_text)),
+            Err(e) => Status::Failed(e),
+        };
+        *status.lock().unwrap() = new_status;
+    });
+}
+
+fn update_submit_text(
+    status: Res<SubmitStatus>,
+    locale: Res<Locale>,
+    mut text_q: Query<&mut Text, With<SubmitText>>,
+) {
+    let Ok(mut text) = text_q.get_single_mut() else {
+        return;
+    };
+
+    let value = match &*status.0.lock().unwrap() {
+        Status::Idle => String::new(),
+        Status::Sending => locale.get("submitting"),
+        Status::Done(SubmitResponse::Accepted { rank }) => {
+            locale.fmt("ranked", &[("rank", rank.to_string())])
+        }
+        Status::Done(SubmitResponse::Rejected { reason }) => {
+            locale.fmt("rejected", &[("reason", reason.clone())])
+        }
+        Status::Failed(e) => locale.fmt("unreachable", &[("error", e.clone())]),
+    };
+
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
@@ -0,0 +1,384 @@
+//! Picks the ball texture quality, and how much else to spend per frame, from how long frames
+//! actually take.
+//!
+//! Frame times are judged a window at a time by their median and 95th percentile against the
+//! display's refresh interval, which is learnt from the fastest frames in each steady window, so
+//! 30, 50, 60 and 144 Hz screens are all held to what they can show. Going up takes several good
+//! windows in a row and going down takes two bad ones. A level that had to be dropped from isn't
+//! tried again for a while, and for longer each time it fails.
+//!
+//! Physics substeps are part of the rules and stay put, so that runs still verify; only what's
+//! drawn is scaled.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use pumpkin_game::assets::QUALITIES;
+
+use crate::GameState;
+
+/// Quality the game starts at, dropping as needed.
+pub const START_QUALITY: usize = 512;
+
+/// Frames judged at a time, about two seconds at 60 Hz.
+const WINDOW_FRAMES: usize = 120;
+const UPGRADE_WINDOWS: u32 = 3;
+const DOWNGRADE_WINDOWS: u32 = 2;
+/// Upgrade only if nearly every frame makes the refresh...
+const UPGRADE_P95: f32 = 1.1;
+/// ...and downgrade if a typical frame misses it, or the slow ones miss it badly.
+const DOWNGRADE_P50: f32 = 1.3;
+const DOWNGRADE_P95: f32 = 2.0;
+/// How long a level that was dropped from is off limits, doubling each time it fails again.
+const CEILING_SECS: f32 = 20.0;
+const MAX_CEILING_SECS: f32 = 300.0;
+/// Frames longer than this are hitches (loading, the tab coming back), not a measure of anything.
+const MAX_FRAME_SECS: f32 = 0.25;
+/// A window only says what the refresh rate is if its slow frames are this close to its fast
+/// ones. A device that's struggling is all over the place, and shouldn't talk itself into
+/// thinking it has a slow screen.
+const STEADY_SPREAD: f32 = 1.15;
+
+const REFRESH_RATES: &[f32] = &[
+    30.0, 50.0, 60.0, 72.0, 75.0, 90.0, 120.0, 144.0, 165.0, 240.0,
+];
+
+pub struct QualityPlugin;
+
+impl Plugin for QualityPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Quality(START_QUALITY))
+            .init_resource::<QualityController>()
+            .init_resource::<EffectsBudget>()
+            .add_systems(
+                Update,
+                (record_frame_time, change_quality, adaptive_quality)
+                    .chain()
+                    .run_if(in_state(GameState::Running)),
+            );
+    }
+}
+
+/// Ball texture size in pixels, one of [`QUALITIES`].
+#[derive(Resource)]
+pub struct Quality(pub usize);
+
+/// How much of the optional eye candy to draw, from 0 to 1. Effects scale how many of themselves
+/// they run at once by it, with [`EffectsBudget::scale`].
+#[derive(Resource)]
+pub struct EffectsBudget(pub f32);
+
+impl Default for EffectsBudget {
+    fn default() -> Self {
+        EffectsBudget(1.0)
+    }
+}
+
+impl EffectsBudget {
+    /// `full` cut down to the budget, keeping at least one.
+    pub fn scale(&self, full: usize) -> usize {
+        ((full as f32 * self.0).round() as usize).clamp(1, full.max(1))
+    }
+}
+
+#[derive(Resource)]
+pub struct QualityController {
+    frames: VecDeque<f32>,
+    good_windows: u32,
+    bad_windows: u32,
+    /// The level last dropped from, with how long until it can be tried again.
+    ceiling: Option<(usize, f32)>,
+    ceiling_secs: f32,
+    /// Learnt from the first window, and again from any steady one after.
+    pub display_hz: Option<f32>,
+    pub p50: f32,
+    pub p95: f32,
+    /// What the controller last did and why, for the dev overlay.
+    pub last_decision: String,
+}
+
+impl Default for QualityController {
+    fn default() -> Self {
+        QualityController {
+            frames: VecDeque::with_capacity(WINDOW_FRAMES),
+            good_windows: 0,
+            bad_windows: 0,
+            ceiling: None,
+            ceiling_secs: CEILING_SECS,
+            display_hz: None,
+            p50: 0.0,
+            p95: 0.0,
+            last_decision: "measuring".into(),
+        }
+    }
+}
+
+fn level(quality: usize) -> usize {
+    QUALITIES.iter().position(|&q| q == quality).unwrap_or(0)
+}
+
+fn percentile(sorted: &[f32], p: f32) -> f32 {
+    sorted[((sorted.len() - 1) as f32 * p).round() as usize]
+}
+
+/// The common refresh rate closest to `hz`.
+fn snap_refresh(hz: f32) -> f32 {
+    REFRESH_RATES
+        .iter()
+        .copied()
+        .min_by(|a, b| (a - hz).abs().total_cmp(&(b - hz).abs()))
+        .unwrap_or(60.0)
+}
+
+#[derive(Debug, PartialEq)]
+enum Verdict {
+    Up,
+    Down,
+    Stay,
+}
+
+impl QualityController {
+    /// What the controller is seeing and doing, a few lines for the dev overlay.
+    pub fn report(&self) -> String {
+        let ceiling = match self.ceiling {
+            Some((q, secs)) if secs > 0.0 => format!("{} for {:.0}s", q, secs),
+            _ => "none".into(),
+        };
+        let display = self
+            .display_hz
+            .map_or("measuring".into(), |hz| format!("{}Hz", hz));
+        format!(
+            "display {}  p50 {:.1}ms  p95 {:.1}ms\nceiling {}\n{}",
+            display,
+            self.p50 * 1000.0,
+            self.p95 * 1000.0,
+            ceiling,
+            self.last_decision,
+        )
+    }
+
+    /// Judges a full window of frames, or returns `None` until there is one.
+    fn judge(&mut self) -> Option<Verdict> {
+        if self.frames.len() < WINDOW_FRAMES {
+            return None;
+        }
+
+        let mut sorted: Vec<f32> = self.frames.drain(..).collect();
+        sorted.sort_by(f32::total_cmp);
+        self.p50 = percentile(&sorted, 0.5);
+        self.p95 = percentile(&sorted, 0.95);
+
+        // with vsync the fastest frames sit right on the refresh interval
+        let fastest = percentile(&sorted, 0.1);
+        let steady = self.p95 <= fastest * STEADY_SPREAD;
+        let display_hz = match self.display_hz {
+            Some(hz) if !steady => hz,
+            _ => snap_refresh(1.0 / fastest),
+        };
+        self.display_hz = Some(display_hz);
+
+        let interval = 1.0 / display_hz;
+        if self.p50 > interval * DOWNGRADE_P50 || self.p95 > interval * DOWNGRADE_P95 {
+            self.good_windows = 0;
+            self.bad_windows += 1;
+        } else if self.p95 <= interval * UPGRADE_P95 {
+            self.bad_windows = 0;
+            self.good_windows += 1;
+        } else {
+            self.good_windows = 0;
+            self.bad_windows = 0;
+        }
+
+        Some(if self.bad_windows >= DOWNGRADE_WINDOWS {
+            Verdict::Down
+        } else if self.good_windows >= UPGRADE_WINDOWS {
+            Verdict::Up
+        } else {
+            Verdict::Stay
+        })
+    }
+}
+
+/// Only frames played count: menus are cheap, and would make the first window look better than
+/// the game can do.
+fn record_frame_time(mut controller: ResMut<QualityController>, time: Res<Time>) {
+    // real time, so slow motion doesn't read as a fast machine
+    let dt = time.raw_delta_seconds();
+    if dt > 0.0 && dt < MAX_FRAME_SECS {
+        if controller.frames.len() == WINDOW_FRAMES {
+            controller.frames.pop_front();
+        }
+        controller.frames.push_back(dt);
+    }
+}
+
+fn change_quality(
+    keys: Res<Input<KeyCode>>,
+    mut quality: ResMut<Quality>,
+    mut controller: ResMut<QualityController>,
+) {
+    if keys.just_pressed(KeyCode::Q) {
+        quality.0 = QUALITIES[(level(quality.0) + 1) % QUALITIES.len()];
+        controller.last_decision = format!("set to {} by hand", quality.0);
+    }
+}
+
+fn adaptive_quality(
+    mut quality: ResMut<Quality>,
+    mut controller: ResMut<QualityController>,
+    mut budget: ResMut<EffectsBudget>,
+    time: Res<Time>,
+) {
+    // the level stays remembered once it's allowed again, so failing it twice backs off longer
+    if let Some((q, secs)) = &mut controller.ceiling {
+        if *secs > 0.0 {
+            *secs -= time.raw_delta_seconds();
+            if *secs <= 0.0 {
+                controller.last_decision = format!("{} allowed again", q);
+            }
+        }
+    }
+
+    let Some(verdict) = controller.judge() else {
+        return;
+    };
+
+    let current = level(quality.0);
+    match verdict {
+        Verdict::Down if current > 0 => {
+            // back off from this level for longer if it's failed before
+            controller.ceiling_secs = match controller.ceiling {
+                Some((q, _)) if q == quality.0 => {
+                    (controller.ceiling_secs * 2.0).min(MAX_CEILING_SECS)
+                }
+                _ => CEILING_SECS,
+            };
+            controller.ceiling = Some((quality.0, controller.ceiling_secs));
+            controller.last_decision = format!(
+                "down from {}: p50 {:.1}ms p95 {:.1}ms at {}Hz, held off {:.0}s",
+                quality.0,
+                controller.p50 * 1000.0,
+                controller.p95 * 1000.0,
+                controller.display_hz.unwrap_or_default(),
+                controller.ceiling_secs
+            );
+            quality.0 = QUALITIES[current - 1];
+            controller.bad_windows = 0;
+        }
+        Verdict::Up if current + 1 < QUALITIES.len() => {
+            let next = QUALITIES[current + 1];
+            match controller.ceiling {
+                Some((q, secs)) if next >= q && secs > 0.0 => {
+                    controller.last_decision = format!("{} held off for {:.0}s", q, secs);
+                }
+                _ => {
+                    controller.last_decision = format!("up to {}", next);
+                    quality.0 = next;
+                }
+            }
+            controller.good_windows = 0;
+        }
+        _ => {}
+    }
+
+    if quality.is_changed() {
+        budget.0 = (level(quality.0) + 1) as f32 / QUALITIES.len() as f32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fills a window with frames of `ms`, every `every`th one taking `slow_ms` instead.
+    fn window(controller: &mut QualityController, ms: f32, slow_ms: f32, every: usize) {
+        for i in 0..WINDOW_FRAMES {
+            let ms = if every > 0 && i % every == 0 {
+                slow_ms
+            } else {
+                ms
+            };
+            controller.frames.push_back(ms / 1000.0);
+        }
+    }
+
+    #[test]
+    fn percentiles_pick_from_sorted_frames() {
+        let sorted: Vec<f32> = (1..=11).map(|i| i as f32).collect();
+        assert_eq!(percentile(&sorted, 0.0), 1.0);
+        assert_eq!(percentile(&sorted, 0.5), 6.0);
+        assert_eq!(percentile(&sorted, 1.0), 11.0);
+    }
+
+    #[test]
+    fn refresh_rates_snap_to_common_ones() {
+        assert_eq!(snap_refresh(29.0), 30.0);
+        assert_eq!(snap_refresh(49.5), 50.0);
+        assert_eq!(snap_refresh(59.94), 60.0);
+        assert_eq!(snap_refresh(141.0), 144.0);
+    }
+
+    #[test]
+    fn judging_waits_for_a_full_window() {
+        let mut controller = QualityController::default();
+        controller.frames.push_back(0.016);
+        assert!(controller.judge().is_none());
+    }
+
+    #[test]
+    fn a_steady_50hz_display_upgrades() {
+        let mut controller = QualityController::default();
+        for _ in 0..UPGRADE_WINDOWS - 1 {
+            window(&mut controller, 20.0, 0.0, 0);
+            assert_eq!(controller.judge(), Some(Verdict::Stay));
+        }
+        window(&mut controller, 20.0, 0.0, 0);
+        assert_eq!(controller.judge(), Some(Verdict::Up));
+        assert_eq!(controller.display_hz, Some(50.0));
+    }
+
+    #[test]
+    fn the_display_rate_can_come_down() {
+        let mut controller = QualityController::default();
+        window(&mut controller, 16.7, 0.0, 0);
+        controller.judge();
+        assert_eq!(controller.display_hz, Some(60.0));
+
+        // the window moved to a 30 Hz screen
+        window(&mut controller, 33.3, 0.0, 0);
+        assert_eq!(controller.judge(), Some(Verdict::Stay));
+        assert_eq!(controller.display_hz, Some(30.0));
+    }
+
+    #[test]
+    fn struggling_frames_downgrade_without_relearning() {
+        let mut controller = QualityController::default();
+        window(&mut controller, 16.7, 0.0, 0);
+        controller.judge();
+
+        for verdict in [Verdict::Stay, Verdict::Down] {
+            window(&mut controller, 20.0, 45.0, 3);
+            assert_eq!(controller.judge(), Some(verdict));
+            assert_eq!(controller.display_hz, Some(60.0));
+        }
+    }
+
+    #[test]
+    fn the_buffer_keeps_one_window() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<QualityController>()
+            .add_systems(Update, record_frame_time);
+        for _ in 0..WINDOW_FRAMES * 2 {
+            app.update();
+        }
+        assert!(app.world.resource::<QualityController>().frames.len() <= WINDOW_FRAMES);
+    }
+
+    #[test]
+    fn the_budget_keeps_at_least_one() {
+        assert_eq!(EffectsBudget(1.0).scale(8), 8);
+        assert_eq!(EffectsBudget(0.5).scale(8), 4);
+        assert_eq!(EffectsBudget(0.0).scale(8), 1);
+    }
+}
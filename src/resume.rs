@@ -0,0 +1,163 @@
+//! Putting a run down and picking it back up. The board is saved when the game loses focus, is
+//! closed, or is left for the menu, and the splash screen offers to continue it.
+//!
+//! What the client keeps track of about the run, like achievement progress, is saved alongside
+//! the board, so a continued run doesn't get a clean slate.
+
+use bevy::{app::AppExit, prelude::*, window::WindowCloseRequested};
+use pumpkin_game::{
+    replay::Replay,
+    save::{SavedRun, SAVE_VERSION},
+    sim::{GameMode, RunOver},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    achievements::RunProgress, dev::ConsoleUsed, stats::RunStats, storage, AppState, GameState,
+};
+
+const STORAGE_KEY: &str = "run";
+
+pub struct ResumePlugin;
+
+impl Plugin for ResumePlugin {
+    fn build(&self, app: &mut App) {
+        let run = storage::load::<SuspendedRun>(STORAGE_KEY).filter(|saved| {
+            if saved.run.version != SAVE_VERSION {
+                warn!("discarding saved run from version {}", saved.run.version);
+            }
+            saved.run.version == SAVE_VERSION
+        });
+
+        app.insert_resource(SavedGame { run, ..default() })
+            .add_systems(
+                OnEnter(AppState::Paused),
+                suspend_run.run_if(in_state(GameState::Running)),
+            )
+            .add_systems(
+                Last,
+                suspend_run.run_if(in_state(GameState::Running).and_then(quitting)),
+            )
+            .add_systems(OnEnter(GameState::GameOver), clear_saved_run)
+            .add_systems(Update, continue_button.run_if(in_state(GameState::Splash)));
+    }
+}
+
+/// A run as it's saved: the board, plus the client's own bookkeeping about it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuspendedRun {
+    #[serde(flatten)]
+    pub run: SavedRun,
+    pub progress: RunProgress,
+    pub stats: RunStats,
+    /// Saves from before the console was marked on runs don't have this.
+    #[serde(default)]
+    pub console: ConsoleUsed,
+}
+
+/// The last run put down, if it hasn't been finished since.
+#[derive(Resource, Default)]
+pub struct SavedGame {
+    pub run: Option<SuspendedRun>,
+    /// Set to pick `run` back up when the next run starts, rather than starting fresh.
+    resume: bool,
+    /// Whether the run in progress was picked back up from a save. Its replay won't verify, so
+    /// it isn't offered to the leaderboard.
+    pub restored: bool,
+}
+
+#[derive(Component)]
+pub struct ContinueButton;
+
+fn quitting(exit_er: EventReader<AppExit>, close_er: EventReader<WindowCloseRequested>) -> bool {
+    !exit_er.is_empty() || !close_er.is_empty()
+}
+
+/// Saves the run in progress, if there is one. Challenges aren't saved, as their stars are for
+/// playing one through in a sitting.
+pub fn suspend_run(world: &mut World) {
+    if world.resource::<RunOver>().0 || world.resource::<Replay>().mode == GameMode::Challenge {
+        return;
+    }
+
+    let run = SuspendedRun {
+        run: SavedRun::capture(world),
+        progress: world.resource::<RunProgress>().clone(),
+        stats: world.resource::<RunStats>().clone(),
+        console: *world.resource::<ConsoleUsed>(),
+    };
+    storage::save(STORAGE_KEY, &run);
+    world.resource_mut::<SavedGame>().run = Some(run);
+}
+
+/// Picks the saved run back up if that's what was asked for. Starting a fresh run throws the
+/// old one away.
+pub fn restore_run(world: &mut World) {
+    let mut saved = world.resource_mut::<SavedGame>();
+    let resume = std::mem::take(&mut saved.resume);
+    saved.restored = false;
+    let run = saved.run.take();
+    storage::remove(STORAGE_KEY);
+
+    let Some(saved) = run.filter(|_| resume) else {
+        return;
+    };
+    if let Err(e) = saved.run.restore(world) {
+        warn!("couldn't continue saved run: {}", e);
+        return;
+    }
+    world.resource_mut::<SavedGame>().restored = true;
+    world.insert_resource(saved.progress);
+    world.insert_resource(saved.stats);
+    // slowmo left on from before counts as well
+    world.resource_mut::<ConsoleUsed>().0 |= saved.console.0;
+}
+
+fn clear_saved_run(mut saved: ResMut<SavedGame>) {
+    saved.run = None;
+    storage::remove(STORAGE_KEY);
+}
+
+fn continue_button(
+    button_q: Query<&Interaction, (Changed<Interaction>, With<ContinueButton>)>,
+    mut saved: ResMut<SavedGame>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if button_q.iter().any(|i| *i == Interaction::Pressed) && saved.run.is_some() {
+        saved.resume = true;
+        next_state.0 = Some(GameState::Running);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pumpkin_game::{replay::headless_app, sim::reset_run};
+
+    fn suspended() -> SuspendedRun {
+        let mut app = headless_app();
+        reset_run(&mut app.world);
+        SuspendedRun {
+            run: SavedRun::capture(&mut app.world),
+            progress: RunProgress::default(),
+            stats: RunStats {
+                drops: 12,
+                max_multiplier: 3,
+                ..default()
+            },
+            console: ConsoleUsed(true),
+        }
+    }
+
+    #[test]
+    fn the_bookkeeping_is_saved_with_the_board() {
+        let saved = suspended();
+        let json = serde_json::to_string(&saved).unwrap();
+        let loaded: SuspendedRun = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded.run, saved.run);
+        assert_eq!(loaded.progress, saved.progress);
+        assert_eq!(loaded.stats, saved.stats);
+        assert!(loaded.console.0);
+    }
+}
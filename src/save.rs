@@ -0,0 +1,316 @@
+//! Snapshots of a run in progress, so it can be put down and picked up again later.
+//!
+//! Unlike a [`Replay`], a [`SavedRun`] is the board itself: every ball as it lies, plus the score,
+//! the queue and the RNG state. Physics doesn't come back bit for bit, so a resumed run plays on
+//! fine but its replay won't verify.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chaos::Chaos,
+    level::{add_level, Level},
+    replay::Replay,
+    sim::{
+        ball_bundle, BallSize, ComboTimer, GameMode, GameRng, Multiplier, NextBallSize,
+        NextBallTimer, NextNextBallSize, RunConfig, Score, SettleTimer, SimEntity, SimParams,
+        SimTick, BALL_ORDER, OVERTOP_TIMER,
+    },
+};
+
+/// Bumped whenever the save format changes in a way old saves can't be loaded into.
+pub const SAVE_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedBall {
+    pub position: [f32; 2],
+    /// Radians.
+    pub rotation: f32,
+    pub linear_velocity: [f32; 2],
+    pub angular_velocity: f32,
+    pub size: usize,
+    /// How long the ball has been over the death line, in seconds.
+    pub settle: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedRun {
+    pub version: u32,
+    pub seed: u64,
+    pub mode: GameMode,
+    /// Missing from saves made before there were other levels, which were all in the classic box.
+    #[serde(default)]
+    pub level: Level,
+    pub tick: u64,
+    pub score: usize,
+    pub multiplier: usize,
+    /// Seconds left for the combo in progress to carry on.
+    pub combo_timer: f32,
+    pub next: usize,
+    pub next_next: usize,
+    pub rng: u64,
+    /// Seconds since the last drop, capped at the drop delay.
+    pub next_ball_timer: f32,
+    pub balls: Vec<SavedBall>,
+    /// Where a chaos run's modifiers were up to. Saves from other runs don't need it.
+    #[serde(default)]
+    pub chaos: Chaos,
+    pub replay: Replay,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SaveError {
+    Version(u32),
+    /// A ball that couldn't have come from this build.
+    BadBall(usize),
+    BadLevel,
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Version(v) => write!(f, "save version {} (expected {})", v, SAVE_VERSION),
+            SaveError::BadBall(i) => write!(f, "ball {} is invalid", i),
+            SaveError::BadLevel => write!(f, "the level is invalid"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl SavedRun {
+    /// Snapshots the run currently in `world`.
+    pub fn capture(world: &mut World) -> Self {
+        let balls = world
+            .query_filtered::<(
+                &Position,
+                &Rotation,
+                &LinearVelocity,
+                &AngularVelocity,
+                &BallSize,
+                &SettleTimer,
+            ), With<SimEntity>>()
+            .iter(world)
+            .map(|(pos, rot, lv, av, size, settle)| SavedBall {
+                position: pos.0.to_array(),
+                rotation: rot.as_radians(),
+                linear_velocity: lv.0.to_array(),
+                angular_velocity: av.0,
+                size: size.0,
+                settle: settle.0.elapsed_secs(),
+            })
+            .collect();
+
+        let config = world.resource::<RunConfig>();
+
+        SavedRun {
+            version: SAVE_VERSION,
+            seed: config.seed,
+            mode: config.mode,
+            level: world.resource::<Level>().clone(),
+            tick: world.resource::<SimTick>().0,
+            score: world.resource::<Score>().0,
+            multiplier: world.resource::<Multiplier>().0,
+            combo_timer: world.resource::<ComboTimer>().0,
+            next: world.resource::<NextBallSize>().0,
+            next_next: world.resource::<NextNextBallSize>().0,
+            rng: world.resource::<GameRng>().0.get_seed(),
+            next_ball_timer: world.resource::<NextBallTimer>().0.elapsed_secs(),
+            balls,
+            chaos: world.resource::<Chaos>().clone(),
+            replay: world.resource::<Replay>().clone(),
+        }
+    }
+
+    /// Puts the run back into `world`, which should have had [`reset_run`] called on it and
+    /// nothing else.
+    ///
+    /// [`reset_run`]: crate::sim::reset_run
+    pub fn restore(&self, world: &mut World) -> Result<(), SaveError> {
+        if self.version != SAVE_VERSION {
+            return Err(SaveError::Version(self.version));
+        }
+        if self.level.check().is_err() {
+            return Err(SaveError::BadLevel);
+        }
+        let valid_size = |size: usize| size < BALL_ORDER.len();
+        if !valid_size(self.next) || !valid_size(self.next_next) {
+            return Err(SaveError::BadBall(0));
+        }
+        for (i, ball) in self.balls.iter().enumerate() {
+            let finite = ball
+                .position
+                .iter()
+                .chain(&ball.linear_velocity)
+                .all(|v| v.is_finite())
+                && ball.rotation.is_finite()
+                && ball.angular_velocity.is_finite();
+            if !valid_size(ball.size) || !finite {
+                return Err(SaveError::BadBall(i));
+            }
+        }
+
+        // the box reset_run built may not be the one the run was in
+        if *world.resource::<Level>() != self.level {
+            let parts: Vec<Entity> = world
+                .query_filtered::<Entity, (With<SimEntity>, Without<BallSize>)>()
+                .iter(world)
+                .collect();
+            for entity in parts {
+                world.despawn(entity);
+            }
+            add_level(world, &self.level);
+            world.insert_resource(self.level.clone());
+        }
+
+        world.insert_resource(RunConfig {
+            seed: self.seed,
+            mode: self.mode,
+            level: self.level.clone(),
+        });
+        world.insert_resource(SimTick(self.tick));
+        world.insert_resource(Score(self.score));
+        world.insert_resource(Multiplier(self.multiplier));
+        world.insert_resource(ComboTimer(self.combo_timer));
+        world.insert_resource(NextBallSize(self.next));
+        world.insert_resource(NextNextBallSize(self.next_next));
+        world.insert_resource(GameRng(fastrand::Rng::with_seed(self.rng)));
+        world
+            .resource_mut::<NextBallTimer>()
+            .0
+            .set_elapsed(Duration::from_secs_f32(self.next_ball_timer));
+        world.insert_resource(self.chaos.clone());
+        world.insert_resource(self.replay.clone());
+
+        let params = world.resource::<SimParams>().clone();
+        for ball in &self.balls {
+            let position = Vec2::from(ball.position);
+            let mut settle = SettleTimer(Timer::from_seconds(OVERTOP_TIMER, TimerMode::Once));
+            settle.0.set_elapsed(Duration::from_secs_f32(ball.settle));
+
+            world
+                .spawn(ball_bundle(
+                    &params,
+                    position,
+                    ball.size,
+                    ball.angular_velocity,
+                ))
+                .insert((
+                    Rotation::from_radians(ball.rotation),
+                    LinearVelocity(Vec2::from(ball.linear_velocity)),
+                    settle,
+                    Transform::from_translation(position.extend(0.0))
+                        .with_rotation(Quat::from_rotation_z(ball.rotation)),
+                ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        replay::{headless_app, step},
+        sim::{reset_run, DropInput, RunOver},
+    };
+
+    /// A run a few drops in, with balls still moving.
+    fn run_in_progress(seed: u64) -> App {
+        let mut app = headless_app();
+        app.insert_resource(RunConfig { seed, ..default() });
+        reset_run(&mut app.world);
+
+        let mut dropped = 0;
+        for _ in 0..60 * 8 {
+            if app.world.resource::<NextBallTimer>().0.finished() {
+                app.world.resource_mut::<DropInput>().0 = Some((dropped % 7) as f32 * 0.4 - 1.2);
+                dropped += 1;
+            }
+            step(&mut app);
+        }
+        assert!(!app.world.resource::<RunOver>().0);
+        app
+    }
+
+    fn sorted(mut balls: Vec<SavedBall>) -> Vec<SavedBall> {
+        balls.sort_by(|a, b| a.position[0].total_cmp(&b.position[0]));
+        balls
+    }
+
+    #[test]
+    fn restore_puts_back_what_was_captured() {
+        let mut app = run_in_progress(5);
+        let saved = SavedRun::capture(&mut app.world);
+        assert!(!saved.balls.is_empty());
+
+        let json = serde_json::to_string(&saved).unwrap();
+        let parsed: SavedRun = serde_json::from_str(&json).unwrap();
+
+        let mut resumed = headless_app();
+        reset_run(&mut resumed.world);
+        parsed.restore(&mut resumed.world).unwrap();
+        let again = SavedRun::capture(&mut resumed.world);
+
+        assert_eq!(
+            (again.seed, again.tick, again.score, again.multiplier),
+            (saved.seed, saved.tick, saved.score, saved.multiplier)
+        );
+        assert_eq!(
+            (again.next, again.next_next, again.rng),
+            (saved.next, saved.next_next, saved.rng)
+        );
+        assert_eq!(again.replay, saved.replay);
+        assert!((again.next_ball_timer - saved.next_ball_timer).abs() < 1e-4);
+        assert_eq!(again.combo_timer, saved.combo_timer);
+
+        // angles and timers go through trig and `Duration`, so only come back nearly the same
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-4;
+        let (balls, again_balls) = (sorted(saved.balls), sorted(again.balls));
+        assert_eq!(balls.len(), again_balls.len());
+        for (a, b) in balls.iter().zip(&again_balls) {
+            assert_eq!(
+                (a.position, a.linear_velocity, a.angular_velocity, a.size),
+                (b.position, b.linear_velocity, b.angular_velocity, b.size)
+            );
+            assert!(close(a.rotation, b.rotation) && close(a.settle, b.settle));
+        }
+    }
+
+    #[test]
+    fn restore_rejects_other_versions() {
+        let mut app = run_in_progress(6);
+        let mut saved = SavedRun::capture(&mut app.world);
+        saved.version = SAVE_VERSION + 1;
+
+        let mut resumed = headless_app();
+        reset_run(&mut resumed.world);
+        assert_eq!(
+            saved.restore(&mut resumed.world),
+            Err(SaveError::Version(SAVE_VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn restore_rebuilds_the_level() {
+        let mut app = run_in_progress(7);
+        let mut saved = SavedRun::capture(&mut app.world);
+        saved.level = Level::built_in("pegs").unwrap();
+
+        let mut resumed = headless_app();
+        reset_run(&mut resumed.world);
+        saved.restore(&mut resumed.world).unwrap();
+
+        let pegs = resumed
+            .world
+            .query::<&crate::level::Piece>()
+            .iter(&resumed.world)
+            .count();
+        assert_eq!(pegs, saved.level.obstacles.len());
+        assert_eq!(*resumed.world.resource::<Level>(), saved.level);
+    }
+}
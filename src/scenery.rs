@@ -0,0 +1,78 @@
+//! Drawing the [`Level`] a run is played in. The box art is stretched to the level's box, and
+//! anything the art doesn't show, like pegs, bars and a tipped floor, is drawn as a flat shape.
+
+use bevy::{prelude::*, sprite::Mesh2dHandle};
+use pumpkin_game::{
+    level::{Level, Piece},
+    sim::{BOX_HEIGHT, BOX_WIDTH, TOP_OFFSET},
+};
+
+const PIECE_COLOR: Color = Color::rgb(0.35, 0.22, 0.45);
+
+pub struct SceneryPlugin;
+
+impl Plugin for SceneryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, build_piece_material).add_systems(
+            Update,
+            (
+                fit_box_art.run_if(resource_changed::<Level>()),
+                dress_pieces,
+            ),
+        );
+    }
+}
+
+/// The background and foreground, drawn for the classic box.
+#[derive(Component)]
+pub struct BoxArt;
+
+#[derive(Resource)]
+struct PieceMaterial(Handle<ColorMaterial>);
+
+fn build_piece_material(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    commands.insert_resource(PieceMaterial(
+        materials.add(ColorMaterial::from(PIECE_COLOR)),
+    ));
+}
+
+/// Stretches the art about the top of the box, which stays put whatever the level.
+fn fit_box_art(mut art_q: Query<&mut Transform, With<BoxArt>>, level: Res<Level>) {
+    let top = TOP_OFFSET + BOX_HEIGHT / 2.0;
+    let scale = Vec2::new(level.width / BOX_WIDTH, level.height / BOX_HEIGHT);
+
+    for mut transform in art_q.iter_mut() {
+        transform.scale = scale.extend(1.0);
+        transform.translation.y = top * (1.0 - scale.y);
+    }
+}
+
+fn dress_pieces(
+    piece_q: Query<(Entity, &Piece), Added<Piece>>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    material: Res<PieceMaterial>,
+) {
+    for (entity, piece) in piece_q.iter() {
+        match *piece {
+            Piece::Disc { radius } => {
+                commands.entity(entity).insert((
+                    Mesh2dHandle(meshes.add(shape::Circle::new(radius).into())),
+                    material.0.clone_weak(),
+                    VisibilityBundle::default(),
+                ));
+            }
+            Piece::Bar { length, thickness } => {
+                commands.entity(entity).insert((
+                    Sprite {
+                        color: PIECE_COLOR,
+                        custom_size: Some(Vec2::new(length, thickness)),
+                        ..default()
+                    },
+                    Handle::<Image>::default(),
+                    VisibilityBundle::default(),
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,810 @@
+//! The game rules, free of anything that needs a window, a GPU or a speaker.
+//!
+//! Everything in here runs in `FixedUpdate` at [`SIM_HZ`], physics included, so a run is fully
+//! determined by its seed and the ticks its drops happened on. The client dresses the entities
+//! this spawns with sprites and sounds; the leaderboard server runs it headless to check replays.
+
+use std::{f32::consts::PI, time::Duration};
+
+use bevy::prelude::*;
+use bevy_xpbd_2d::{prelude::*, PhysicsSet};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chaos::{chaos_run, run_chaos, Chaos, ChaosEvent, TiltInput},
+    level::{add_level, Level},
+    replay::{Replay, ReplayDrop},
+};
+
+pub const SIM_HZ: f32 = 60.0;
+pub const SIM_DT: f32 = 1.0 / SIM_HZ;
+
+pub const DROP_LINE: f32 = 3.0;
+
+pub const BOX_WIDTH: f32 = 4.4;
+pub const BOX_HEIGHT: f32 = 5.0;
+pub const TOP_OFFSET: f32 = -0.8;
+pub const WALL_THICKNESS: f32 = 1.0;
+
+pub const DEATH_LINE: f32 = 2.0;
+pub const OVERTOP_TIMER: f32 = 1.5;
+
+pub const MAX_RADIUS: f32 = (BOX_WIDTH / 2.2) / 2.0;
+pub const MIN_RADIUS: f32 = 0.15;
+
+pub const LINEAR_DAMPING: f32 = 3.0;
+pub const ANGULAR_DAMPING: f32 = 0.7;
+pub const FRICTION: f32 = 0.7;
+pub const RESTITUTION: f32 = 0.5;
+
+pub const G: f32 = 70.0;
+
+pub const COMBO_WINDOW: f32 = 1.0;
+pub const COMBO_END_BONUS: usize = 2;
+
+pub const BALL_ORDER: &[&str] = &[
+    "sweet",
+    "spider",
+    "bat",
+    "apple",
+    "candy_apple",
+    "ghost",
+    "vampire",
+    "mummy",
+    "frankenstein",
+    "skull",
+    "pumpkin",
+];
+
+pub fn lerp(v0: f32, v1: f32, t: f32) -> f32 {
+    v0 + t * (v1 - v0)
+}
+
+pub fn ease_in_cubic(t: f32) -> f32 {
+    t * t * t
+}
+
+pub fn ease_in_circ(t: f32) -> f32 {
+    1.0 - (1.0 - t.powi(2)).sqrt()
+}
+
+pub fn ease_in_sine(t: f32) -> f32 {
+    1.0 - ((t * PI) / 2.0).cos()
+}
+
+/// How ball radius grows from [`MIN_RADIUS`] to [`MAX_RADIUS`] along [`BALL_ORDER`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RadiusEasing {
+    Linear,
+    #[default]
+    Sine,
+    Cubic,
+    Circ,
+}
+
+impl RadiusEasing {
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            RadiusEasing::Linear => t,
+            RadiusEasing::Sine => ease_in_sine(t),
+            RadiusEasing::Cubic => ease_in_cubic(t),
+            RadiusEasing::Circ => ease_in_circ(t),
+        }
+    }
+}
+
+/// The tunable parts of the rules. Normal play always uses the defaults, which are the
+/// constants above; the balance tool sweeps them.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimParams {
+    pub gravity: f32,
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    pub friction: f32,
+    pub restitution: f32,
+    /// Relative chance of each of the smallest sizes coming up next.
+    pub spawn_weights: Vec<f32>,
+    pub radius_easing: RadiusEasing,
+    pub combo: ComboRules,
+}
+
+impl Default for SimParams {
+    fn default() -> Self {
+        SimParams {
+            gravity: G,
+            linear_damping: LINEAR_DAMPING,
+            angular_damping: ANGULAR_DAMPING,
+            friction: FRICTION,
+            restitution: RESTITUTION,
+            spawn_weights: vec![1.0; BALL_ORDER.len() / 2],
+            radius_easing: RadiusEasing::default(),
+            combo: ComboRules::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamsError {
+    /// Negative, or not a number.
+    BadValue(&'static str),
+    /// None at all, more than there are sizes, one negative or not a number, or all of them 0.
+    BadSpawnWeights,
+}
+
+impl std::fmt::Display for ParamsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParamsError::BadValue(name) => write!(f, "{} must be a number, 0 or more", name),
+            ParamsError::BadSpawnWeights => write!(
+                f,
+                "spawn_weights needs 1 to {} weights, 0 or more and not all 0",
+                BALL_ORDER.len()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParamsError {}
+
+impl SimParams {
+    /// Makes sure these rules can be played by. The defaults always can; anything read from
+    /// elsewhere should be checked.
+    pub fn check(&self) -> Result<(), ParamsError> {
+        let values = [
+            ("gravity", self.gravity),
+            ("linear_damping", self.linear_damping),
+            ("angular_damping", self.angular_damping),
+            ("friction", self.friction),
+            ("restitution", self.restitution),
+            ("combo_window", self.combo.window),
+        ];
+        for (name, value) in values {
+            if !value.is_finite() || value < 0.0 {
+                return Err(ParamsError::BadValue(name));
+            }
+        }
+
+        let weights = &self.spawn_weights;
+        let weights_ok = !weights.is_empty()
+            && weights.len() <= BALL_ORDER.len()
+            && weights.iter().all(|w| w.is_finite() && *w >= 0.0)
+            && weights.iter().any(|w| *w > 0.0);
+        if !weights_ok {
+            return Err(ParamsError::BadSpawnWeights);
+        }
+        Ok(())
+    }
+
+    pub fn radius(&self, size: usize) -> f32 {
+        lerp(
+            MIN_RADIUS,
+            MAX_RADIUS,
+            self.radius_easing
+                .apply((size + 1) as f32 / BALL_ORDER.len() as f32),
+        )
+    }
+
+    /// Furthest a ball of `size` can be dropped from the centre of the classic box without
+    /// clipping a wall. See [`Level::drop_limit`] for other boxes.
+    pub fn drop_limit(&self, size: usize) -> f32 {
+        Level::default().drop_limit(self, size)
+    }
+
+    fn pick_size(&self, rng: &mut GameRng) -> usize {
+        let weights = &self.spawn_weights;
+
+        // keep the plain draw for even odds so seeds play out the same as they always have
+        if weights.windows(2).all(|w| w[0] == w[1]) {
+            return rng.0.usize(..weights.len().max(1));
+        }
+
+        let total: f32 = weights.iter().sum();
+        let mut roll = rng.0.f32() * total;
+        for (size, weight) in weights.iter().enumerate() {
+            if roll < *weight {
+                return size;
+            }
+            roll -= weight;
+        }
+        weights.len() - 1
+    }
+}
+
+/// How merges in quick succession add up. Each merge in a combo scores its size times how many
+/// merges the combo has had so far, and a combo of two or more pays a bonus when it ends.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ComboRules {
+    /// Seconds after a merge for another to keep the combo going. Zero ends combos on the next
+    /// drop instead, however long that takes.
+    pub window: f32,
+    /// Points for each merge in a combo of two or more, paid when it ends.
+    pub end_bonus: usize,
+}
+
+impl Default for ComboRules {
+    fn default() -> Self {
+        ComboRules {
+            window: COMBO_WINDOW,
+            end_bonus: COMBO_END_BONUS,
+        }
+    }
+}
+
+impl ComboRules {
+    /// Combos as they were before they had a window: a drop ends them, and they pay nothing extra.
+    pub const PER_DROP: ComboRules = ComboRules {
+        window: 0.0,
+        end_bonus: 0,
+    };
+
+    /// What a combo of `merges` pays when it ends.
+    pub fn bonus(&self, merges: usize) -> usize {
+        if merges >= 2 {
+            merges * self.end_bonus
+        } else {
+            0
+        }
+    }
+}
+
+/// Radius of a ball of `size` under the default rules.
+pub fn ball_radius(size: usize) -> f32 {
+    SimParams::default().radius(size)
+}
+
+/// [`SimParams::drop_limit`] under the default rules.
+pub fn drop_limit(size: usize) -> f32 {
+    SimParams::default().drop_limit(size)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameMode {
+    #[default]
+    Classic,
+    /// Classic, but dealing [`TUTORIAL_BALLS`] first so the tutorial knows what's coming.
+    Tutorial,
+    /// Played for a goal on a set board, dealing from its level's queue.
+    Challenge,
+    /// Classic, with gravity thrown about by [`Chaos`] modifiers.
+    Chaos,
+}
+
+impl GameMode {
+    /// The size of the `n`th ball a run in this mode deals, if it isn't left to chance.
+    pub fn scripted_ball(self, n: usize) -> Option<usize> {
+        match self {
+            GameMode::Classic | GameMode::Challenge | GameMode::Chaos => None,
+            GameMode::Tutorial => TUTORIAL_BALLS.get(n).copied(),
+        }
+    }
+}
+
+/// The first balls of a tutorial run: a pair to merge, then enough sweets to set off a chain by
+/// merging next to the spider the pair made.
+pub const TUTORIAL_BALLS: &[usize] = &[0, 0, 0, 0, 1, 2];
+
+/// What the next run will be started with. Set before entering a run.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct RunConfig {
+    pub seed: u64,
+    pub mode: GameMode,
+    pub level: Level,
+}
+
+/// Every random decision in a run comes from here, never from the global `fastrand`.
+#[derive(Resource)]
+pub struct GameRng(pub fastrand::Rng);
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng(fastrand::Rng::with_seed(0))
+    }
+}
+
+/// Number of simulation steps taken this run.
+#[derive(Resource, Default)]
+pub struct SimTick(pub u64);
+
+/// Set by whatever is playing (mouse, touch, replay, bot) to ask for a drop at this x.
+#[derive(Resource, Default)]
+pub struct DropInput(pub Option<f32>);
+
+/// True when there is no run in progress, or the current one has ended.
+#[derive(Resource)]
+pub struct RunOver(pub bool);
+
+impl Default for RunOver {
+    fn default() -> Self {
+        RunOver(true)
+    }
+}
+
+fn run_in_progress(over: Res<RunOver>) -> bool {
+    !over.0
+}
+
+#[derive(Resource)]
+pub struct NextBallTimer(pub Timer);
+
+impl Default for NextBallTimer {
+    fn default() -> Self {
+        NextBallTimer(Timer::from_seconds(0.5, TimerMode::Once))
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Score(pub usize);
+
+/// Merges in the combo in progress.
+#[derive(Resource, Default)]
+pub struct Multiplier(pub usize);
+
+/// Seconds left for another merge to keep the combo going.
+#[derive(Resource, Default)]
+pub struct ComboTimer(pub f32);
+
+#[derive(Resource, Default)]
+pub struct NextBallSize(pub usize);
+
+#[derive(Resource, Default)]
+pub struct NextNextBallSize(pub usize);
+
+#[derive(Component)]
+pub struct BallSize(pub usize);
+
+#[derive(Component)]
+pub struct SettleTimer(pub Timer);
+
+/// Marks everything the simulation spawned, so a run can be cleared in one go.
+#[derive(Component)]
+pub struct SimEntity;
+
+#[derive(Event)]
+pub struct SpawnBallEvent {
+    pub position: Vec2,
+    pub size: usize,
+    pub av: f32,
+}
+
+#[derive(Event)]
+pub struct BallDroppedEvent {
+    pub position: Vec2,
+    pub size: usize,
+}
+
+#[derive(Event)]
+pub struct BallsMergedEvent {
+    pub position: Vec2,
+    /// Size of the ball the merge produced.
+    pub size: usize,
+    pub multiplier: usize,
+    /// Where the two balls that merged were, and which way they were turned, in radians.
+    pub from: [(Vec2, f32); 2],
+}
+
+#[derive(Event)]
+pub struct ComboEndedEvent {
+    pub merges: usize,
+    /// Points added to the score for it.
+    pub bonus: usize,
+}
+
+#[derive(Event)]
+pub struct GameOverEvent;
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SimSet {
+    /// Before the physics step: consume input.
+    Input,
+    /// After the physics step: react to collisions and spawn what was asked for.
+    Resolve,
+}
+
+pub struct SimPlugin;
+
+impl Plugin for SimPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(PhysicsPlugins::new(FixedUpdate))
+            .insert_resource(FixedTime::new_from_secs(SIM_DT))
+            .insert_resource(PhysicsTimestep::FixedOnce(SIM_DT))
+            .insert_resource(Gravity(Vec2::NEG_Y * G))
+            .init_resource::<SimParams>()
+            .init_resource::<RunConfig>()
+            .init_resource::<Level>()
+            .init_resource::<GameRng>()
+            .init_resource::<SimTick>()
+            .init_resource::<DropInput>()
+            .init_resource::<RunOver>()
+            .init_resource::<NextBallTimer>()
+            .init_resource::<Score>()
+            .init_resource::<Multiplier>()
+            .init_resource::<ComboTimer>()
+            .init_resource::<NextBallSize>()
+            .init_resource::<NextNextBallSize>()
+            .init_resource::<Replay>()
+            .init_resource::<Chaos>()
+            .init_resource::<TiltInput>()
+            .add_event::<SpawnBallEvent>()
+            .add_event::<BallDroppedEvent>()
+            .add_event::<BallsMergedEvent>()
+            .add_event::<ComboEndedEvent>()
+            .add_event::<GameOverEvent>()
+            .add_event::<ChaosEvent>()
+            .configure_sets(
+                FixedUpdate,
+                (
+                    SimSet::Input.before(PhysicsSet::Prepare),
+                    SimSet::Resolve.after(PhysicsSet::Sync),
+                ),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    (
+                        tick_run,
+                        tick_combo,
+                        release_ball,
+                        run_chaos.run_if(chaos_run),
+                    )
+                        .chain()
+                        .in_set(SimSet::Input)
+                        .run_if(run_in_progress),
+                    (merge_on_collision, spawn_ball, check_over_top)
+                        .chain()
+                        .in_set(SimSet::Resolve)
+                        .run_if(run_in_progress),
+                ),
+            );
+    }
+}
+
+/// Starts a fresh run from the current [`RunConfig`]. Clearing out the previous run's
+/// entities is up to the caller.
+pub fn reset_run(world: &mut World) {
+    let config = world.resource::<RunConfig>().clone();
+    let params = world.resource::<SimParams>().clone();
+    let mut rng = fastrand::Rng::with_seed(config.seed);
+
+    // first ball is always one of the three smallest
+    world.resource_mut::<NextNextBallSize>().0 = rng.usize(..3);
+    world.insert_resource(GameRng(rng));
+    world.insert_resource(SimTick(0));
+    world.insert_resource(DropInput(None));
+    world.insert_resource(RunOver(false));
+    world.insert_resource(NextBallTimer::default());
+    world.insert_resource(Score(0));
+    world.insert_resource(Multiplier(0));
+    world.insert_resource(ComboTimer(0.0));
+    world.insert_resource(Replay {
+        level: config.level.clone(),
+        ..Replay::new(config.seed, config.mode)
+    });
+    world.insert_resource(config.level.clone());
+    world.insert_resource(Chaos::new(config.seed));
+    world.insert_resource(Gravity(Vec2::NEG_Y * params.gravity));
+
+    world.resource_scope(|world, mut rng: Mut<GameRng>| {
+        let next_next = world.resource::<NextNextBallSize>().0;
+        world.resource_mut::<NextBallSize>().0 = next_next;
+        world.resource_mut::<NextNextBallSize>().0 = params.pick_size(&mut rng);
+    });
+    if let Some(size) = scripted_ball(&config.level, config.mode, 0) {
+        world.resource_mut::<NextBallSize>().0 = size;
+    }
+    if let Some(size) = scripted_ball(&config.level, config.mode, 1) {
+        world.resource_mut::<NextNextBallSize>().0 = size;
+    }
+
+    add_level(world, &config.level);
+    for ball in &config.level.balls {
+        world.spawn(ball_bundle(
+            &params,
+            config.level.to_world(ball.at),
+            ball.size,
+            0.0,
+        ));
+    }
+}
+
+/// The size of the `n`th ball, if the level's queue or the mode decides it.
+fn scripted_ball(level: &Level, mode: GameMode, n: usize) -> Option<usize> {
+    level.dealt(n).or_else(|| mode.scripted_ball(n))
+}
+
+/// Ends the run on the current tick. Called by the sim itself on an overtop, and by anything
+/// that wants to stop a run early.
+pub fn end_run(over: &mut RunOver, replay: &mut Replay, tick: &SimTick) {
+    over.0 = true;
+    replay.end_tick = tick.0;
+}
+
+fn tick_run(mut tick: ResMut<SimTick>, mut next_ball_timer: ResMut<NextBallTimer>) {
+    tick.0 += 1;
+    next_ball_timer.0.tick(Duration::from_secs_f32(SIM_DT));
+}
+
+/// Pays out the combo in progress, if there is one, and starts the next from nothing.
+fn end_combo(
+    multiplier: &mut Multiplier,
+    score: &mut Score,
+    rules: &ComboRules,
+    ended_ew: &mut EventWriter<ComboEndedEvent>,
+) {
+    if multiplier.0 == 0 {
+        return;
+    }
+
+    let bonus = rules.bonus(multiplier.0);
+    score.0 += bonus;
+    ended_ew.send(ComboEndedEvent {
+        merges: multiplier.0,
+        bonus,
+    });
+    multiplier.0 = 0;
+}
+
+fn tick_combo(
+    mut timer: ResMut<ComboTimer>,
+    mut multiplier: ResMut<Multiplier>,
+    mut score: ResMut<Score>,
+    params: Res<SimParams>,
+    mut ended_ew: EventWriter<ComboEndedEvent>,
+) {
+    if params.combo.window <= 0.0 || multiplier.0 == 0 {
+        return;
+    }
+
+    timer.0 -= SIM_DT;
+    if timer.0 <= 0.0 {
+        end_combo(&mut multiplier, &mut score, &params.combo, &mut ended_ew);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn release_ball(
+    mut input: ResMut<DropInput>,
+    mut next_ball_timer: ResMut<NextBallTimer>,
+    mut multiplier: ResMut<Multiplier>,
+    mut score: ResMut<Score>,
+    mut rng: ResMut<GameRng>,
+    mut replay: ResMut<Replay>,
+    tick: Res<SimTick>,
+    params: Res<SimParams>,
+    level: Res<Level>,
+    mut next_size: ResMut<NextBallSize>,
+    mut next_next_size: ResMut<NextNextBallSize>,
+    mut spawn_ew: EventWriter<SpawnBallEvent>,
+    mut dropped_ew: EventWriter<BallDroppedEvent>,
+    mut ended_ew: EventWriter<ComboEndedEvent>,
+) {
+    let Some(x) = input.0.take() else {
+        return;
+    };
+
+    if !next_ball_timer.0.finished() || level.balls_left(replay.drops.len()) == Some(0) {
+        return;
+    }
+
+    if params.combo.window <= 0.0 {
+        end_combo(&mut multiplier, &mut score, &params.combo, &mut ended_ew);
+    }
+
+    next_ball_timer.0.reset();
+
+    let size = next_size.0;
+    let limit = level.drop_limit(&params, size);
+    let position = Vec2::new(x.clamp(-limit, limit), DROP_LINE);
+    let av = -1.0 + rng.0.f32() * 2.0;
+
+    replay.drops.push(ReplayDrop { tick: tick.0, x });
+
+    spawn_ew.send(SpawnBallEvent { position, size, av });
+    dropped_ew.send(BallDroppedEvent { position, size });
+
+    next_size.0 = next_next_size.0;
+    next_next_size.0 = params.pick_size(&mut rng);
+    // the ball after next is one past the one just dropped
+    if let Some(size) = scripted_ball(&level, replay.mode, replay.drops.len() + 1) {
+        next_next_size.0 = size;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn merge_on_collision(
+    mut collision_event_reader: EventReader<Collision>,
+    ballsize_q: Query<(
+        &BallSize,
+        &Position,
+        &Rotation,
+        &LinearVelocity,
+        &AngularVelocity,
+    )>,
+    mut commands: Commands,
+    mut score: ResMut<Score>,
+    mut multiplier: ResMut<Multiplier>,
+    mut combo_timer: ResMut<ComboTimer>,
+    params: Res<SimParams>,
+    mut spawn_ew: EventWriter<SpawnBallEvent>,
+    mut merged_ew: EventWriter<BallsMergedEvent>,
+) {
+    for Collision(contact) in collision_event_reader.iter() {
+        // Check BallSize component on entities. If present and equal, remove the two contacting
+        // entities and spawn a ball with the next size at the midpoint of the contacting ball's
+        // positions.
+        let entity1 = contact.entity1;
+        let entity2 = contact.entity2;
+
+        if let Ok((ball1, pos1, rot1, lv1, av1)) = ballsize_q.get(entity1) {
+            if let Ok((ball2, pos2, rot2, lv2, av2)) = ballsize_q.get(entity2) {
+                if ball1.0 == ball2.0 {
+                    let size = ball1.0 + 1;
+
+                    if size >= BALL_ORDER.len() {
+                        continue;
+                    }
+
+                    multiplier.0 += 1;
+                    combo_timer.0 = params.combo.window;
+
+                    score.0 += size * multiplier.0;
+
+                    // Magic numbers to stop insane velocities
+                    let _lv = (lv1.0 + lv2.0) / 10.0;
+                    let av = (av1.0 + av2.0) / 4.0;
+
+                    let position = (pos1.0 + pos2.0) / 2.0;
+
+                    spawn_ew.send(SpawnBallEvent { position, size, av });
+                    merged_ew.send(BallsMergedEvent {
+                        position,
+                        size,
+                        multiplier: multiplier.0,
+                        from: [(pos1.0, rot1.as_radians()), (pos2.0, rot2.as_radians())],
+                    });
+
+                    commands.entity(entity1).despawn_recursive();
+                    commands.entity(entity2).despawn_recursive();
+
+                    // one merge per step to prevent doubling stuffs
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Components for a live ball, for anything that needs to put one on the board directly.
+pub fn ball_bundle(params: &SimParams, position: Vec2, size: usize, av: f32) -> impl Bundle {
+    (
+        RigidBody::Dynamic,
+        Collider::ball(params.radius(size)),
+        Position(position),
+        LinearDamping(params.linear_damping),
+        AngularDamping(params.angular_damping),
+        BallSize(size),
+        Friction::new(params.friction),
+        SimEntity,
+        SettleTimer(Timer::from_seconds(OVERTOP_TIMER, TimerMode::Once)),
+        Restitution::new(params.restitution),
+        AngularVelocity(av),
+        TransformBundle::from_transform(Transform::from_translation(position.extend(0.0))),
+    )
+}
+
+fn spawn_ball(mut er: EventReader<SpawnBallEvent>, mut commands: Commands, params: Res<SimParams>) {
+    for ev in er.iter() {
+        commands.spawn(ball_bundle(&params, ev.position, ev.size, ev.av));
+    }
+}
+
+fn check_over_top(
+    mut ball_q: Query<(&Position, &mut SettleTimer, &BallSize)>,
+    mut over: ResMut<RunOver>,
+    mut replay: ResMut<Replay>,
+    tick: Res<SimTick>,
+    params: Res<SimParams>,
+    chaos: Res<Chaos>,
+    mut ew: EventWriter<GameOverEvent>,
+) {
+    let excused = chaos.excuses_overtop();
+    for (pos, mut timer, size) in ball_q.iter_mut() {
+        let ball_top = pos.y + params.radius(size.0);
+        if ball_top > DEATH_LINE && !excused {
+            timer.0.tick(Duration::from_secs_f32(SIM_DT));
+            if timer.0.finished() && !over.0 {
+                end_run(&mut over, &mut replay, &tick);
+                ew.send(GameOverEvent);
+            }
+        } else {
+            timer.0.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draws(params: &SimParams, seed: u64) -> Vec<usize> {
+        let mut rng = GameRng(fastrand::Rng::with_seed(seed));
+        (0..1000).map(|_| params.pick_size(&mut rng)).collect()
+    }
+
+    #[test]
+    fn even_weights_draw_every_size() {
+        let params = SimParams::default();
+        let drawn = draws(&params, 1);
+        for size in 0..params.spawn_weights.len() {
+            assert!(drawn.contains(&size), "{} never came up", size);
+        }
+        assert!(drawn.iter().all(|&size| size < params.spawn_weights.len()));
+    }
+
+    #[test]
+    fn weights_skew_the_draw() {
+        let params = SimParams {
+            spawn_weights: vec![3.0, 0.0, 1.0],
+            ..default()
+        };
+        let drawn = draws(&params, 2);
+        let count = |size| drawn.iter().filter(|&&s| s == size).count();
+        assert_eq!(count(1), 0);
+        assert_eq!(count(0) + count(2), drawn.len());
+        assert!(count(0) > count(2) * 2, "{} to {}", count(0), count(2));
+    }
+
+    #[test]
+    fn unplayable_weights_are_refused() {
+        assert_eq!(SimParams::default().check(), Ok(()));
+        for weights in [
+            vec![],
+            vec![1.0, -1.0],
+            vec![0.0, 0.0],
+            vec![1.0, f32::NAN],
+            vec![1.0; BALL_ORDER.len() + 1],
+        ] {
+            let params = SimParams {
+                spawn_weights: weights.clone(),
+                ..default()
+            };
+            assert_eq!(
+                params.check(),
+                Err(ParamsError::BadSpawnWeights),
+                "{:?}",
+                weights
+            );
+        }
+
+        let params = SimParams {
+            gravity: -1.0,
+            ..default()
+        };
+        assert_eq!(params.check(), Err(ParamsError::BadValue("gravity")));
+    }
+
+    #[test]
+    fn radii_grow_to_the_biggest() {
+        for easing in [
+            RadiusEasing::Linear,
+            RadiusEasing::Sine,
+            RadiusEasing::Cubic,
+            RadiusEasing::Circ,
+        ] {
+            assert!(easing.apply(0.0).abs() < 1e-6, "{:?}", easing);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-6, "{:?}", easing);
+
+            let params = SimParams {
+                radius_easing: easing,
+                ..default()
+            };
+            let radii: Vec<f32> = (0..BALL_ORDER.len()).map(|s| params.radius(s)).collect();
+            assert!(radii[0] > MIN_RADIUS, "{:?}", easing);
+            assert!((radii[BALL_ORDER.len() - 1] - MAX_RADIUS).abs() < 1e-6);
+            assert!(radii.windows(2).all(|w| w[0] < w[1]), "{:?}", easing);
+        }
+    }
+}
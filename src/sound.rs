@@ -0,0 +1,127 @@
+//! Sound effects. Every one-shot is asked for with a [`PlaySound`] and given a voice: at most
+//! [`MAX_VOICES`] play at once, the oldest giving way when another is needed, and anything that
+//! happened on the board is panned by where. Voices go away by themselves when their sound ends.
+
+use std::collections::VecDeque;
+
+use bevy::{
+    audio::{PlaybackMode, Volume, VolumeLevel},
+    prelude::*,
+};
+use pumpkin_game::sim::BOX_WIDTH;
+
+use crate::SoundToggle;
+
+const MAX_VOICES: usize = 12;
+/// Distance between the listener's ears. Sounds are placed between them, a little in front.
+const EAR_GAP: f32 = 2.0;
+/// How far to either side a sound at the wall is panned, from 0 for not at all to 1 for all the
+/// way into one ear.
+const PAN_WIDTH: f32 = 0.7;
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlaySound>()
+            .init_resource::<Voices>()
+            .add_systems(PostUpdate, play_sounds);
+    }
+}
+
+#[derive(Event)]
+pub struct PlaySound {
+    pub source: Handle<AudioSource>,
+    pub volume: f32,
+    pub speed: f32,
+    /// Where on the board it happened, in world units, for sounds that should be panned.
+    pub x: Option<f32>,
+}
+
+impl PlaySound {
+    /// `source` at its own volume and speed, dead centre.
+    pub fn new(source: &Handle<AudioSource>) -> Self {
+        PlaySound {
+            source: source.clone_weak(),
+            volume: 1.0,
+            speed: 1.0,
+            x: None,
+        }
+    }
+
+    pub fn with_volume(self, volume: f32) -> Self {
+        PlaySound { volume, ..self }
+    }
+
+    pub fn with_speed(self, speed: f32) -> Self {
+        PlaySound { speed, ..self }
+    }
+
+    pub fn at(self, x: f32) -> Self {
+        PlaySound { x: Some(x), ..self }
+    }
+}
+
+#[derive(Component)]
+struct Voice;
+
+/// Every voice playing, oldest first.
+#[derive(Resource, Default)]
+struct Voices(VecDeque<Entity>);
+
+fn play_sounds(
+    mut play_er: EventReader<PlaySound>,
+    voice_q: Query<(), With<Voice>>,
+    mut voices: ResMut<Voices>,
+    mut commands: Commands,
+    toggle: Res<SoundToggle>,
+) {
+    if !toggle.0 {
+        play_er.clear();
+        return;
+    }
+
+    // finished voices have despawned themselves
+    voices.0.retain(|&entity| voice_q.contains(entity));
+
+    for ev in play_er.iter() {
+        if voices.0.len() >= MAX_VOICES {
+            if let Some(oldest) = voices.0.pop_front() {
+                commands.entity(oldest).despawn();
+            }
+        }
+
+        let settings = PlaybackSettings {
+            mode: PlaybackMode::Despawn,
+            volume: Volume::Relative(VolumeLevel::new(ev.volume)),
+            speed: ev.speed,
+            ..default()
+        };
+        let voice = match ev.x {
+            Some(x) => {
+                let pan = (x / (BOX_WIDTH / 2.0)).clamp(-1.0, 1.0) * PAN_WIDTH;
+                commands.spawn((
+                    SpatialAudioBundle {
+                        source: ev.source.clone_weak(),
+                        settings,
+                        spatial: SpatialSettings::new(
+                            Transform::IDENTITY,
+                            EAR_GAP,
+                            Vec3::new(pan * EAR_GAP / 2.0, 0.0, 1.0),
+                        ),
+                    },
+                    Voice,
+                ))
+            }
+            None => commands.spawn((
+                AudioBundle {
+                    source: ev.source.clone_weak(),
+                    settings,
+                },
+                Voice,
+            )),
+        }
+        .id();
+        voices.0.push_back(voice);
+    }
+}
@@ -0,0 +1,464 @@
+//! Numbers about how the current run went, shown on the game over screen, and lifetime totals
+//! that are kept between sessions and can be exported.
+
+use bevy::prelude::*;
+use pumpkin_game::{
+    replay::Replay,
+    sim::{BallDroppedEvent, BallsMergedEvent, GameMode, Score, SimTick, BALL_ORDER, SIM_HZ},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dev::ConsoleUsed, layout::SafeArea, locale::Locale, resume, spawn_button, storage, CustomFont,
+    GameState,
+};
+
+const STORAGE_KEY: &str = "stats";
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(storage::load::<LifetimeStats>(STORAGE_KEY).unwrap_or_default())
+            .init_resource::<RunStats>()
+            .add_systems(
+                OnEnter(GameState::Running),
+                reset_run_stats.before(resume::restore_run),
+            )
+            .add_systems(Update, track_run_stats.run_if(in_state(GameState::Running)))
+            .add_systems(OnEnter(GameState::GameOver), record_run)
+            .add_systems(Update, stats_button)
+            .add_systems(OnEnter(GameState::Stats), build_stats)
+            .add_systems(OnExit(GameState::Stats), crate::despawn_with::<StatsTag>)
+            .add_systems(
+                Update,
+                (back_button, export_button).run_if(in_state(GameState::Stats)),
+            );
+    }
+}
+
+/// What happened in the run in progress, or the one that just ended. Saved with the run, so a
+/// continued run carries on counting.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunStats {
+    pub drops: usize,
+    /// How many balls of each size were made by merging.
+    pub merges: Vec<usize>,
+    pub max_multiplier: usize,
+    /// Biggest ball seen this run, as an index into [`BALL_ORDER`].
+    pub largest_tier: usize,
+    pub ticks: u64,
+}
+
+impl Default for RunStats {
+    fn default() -> Self {
+        RunStats {
+            drops: 0,
+            merges: vec![0; BALL_ORDER.len()],
+            max_multiplier: 0,
+            largest_tier: 0,
+            ticks: 0,
+        }
+    }
+}
+
+impl RunStats {
+    fn dropped(&mut self, size: usize) {
+        self.drops += 1;
+        self.largest_tier = self.largest_tier.max(size);
+    }
+
+    fn merged(&mut self, size: usize, multiplier: usize) {
+        self.merges[size] += 1;
+        self.max_multiplier = self.max_multiplier.max(multiplier);
+        self.largest_tier = self.largest_tier.max(size);
+    }
+
+    /// A few lines for the game over screen.
+    pub fn summary(&self, locale: &Locale) -> String {
+        let mut lines = vec![
+            locale.fmt("run-time", &[("time", format_duration(self.ticks))]),
+            locale.fmt("run-drops", &[("drops", self.drops.to_string())]),
+            locale.fmt("run-chain", &[("chain", self.max_multiplier.to_string())]),
+            locale.fmt("run-biggest", &[("ball", locale.ball(self.largest_tier))]),
+        ];
+        let made: Vec<String> = self
+            .merges
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(size, count)| format!("{} {}", count, locale.ball(size)))
+            .collect();
+        if !made.is_empty() {
+            lines.push(locale.fmt("run-made", &[("balls", made.join(", "))]));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Totals over every finished run.
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LifetimeStats {
+    pub games_played: usize,
+    pub best_score: usize,
+    pub total_score: usize,
+    pub total_drops: usize,
+    /// Balls made by merging, by size.
+    pub merges: Vec<usize>,
+    pub best_multiplier: usize,
+    pub largest_tier: usize,
+    pub total_ticks: u64,
+}
+
+impl Default for LifetimeStats {
+    fn default() -> Self {
+        LifetimeStats {
+            games_played: 0,
+            best_score: 0,
+            total_score: 0,
+            total_drops: 0,
+            merges: vec![0; BALL_ORDER.len()],
+            best_multiplier: 0,
+            largest_tier: 0,
+            total_ticks: 0,
+        }
+    }
+}
+
+impl LifetimeStats {
+    pub fn record(&mut self, run: &RunStats, score: usize) {
+        self.games_played += 1;
+        self.best_score = self.best_score.max(score);
+        self.total_score += score;
+        self.total_drops += run.drops;
+        // saves from an older build may have fewer sizes
+        self.merges
+            .resize(BALL_ORDER.len().max(self.merges.len()), 0);
+        for (total, count) in self.merges.iter_mut().zip(&run.merges) {
+            *total += count;
+        }
+        self.best_multiplier = self.best_multiplier.max(run.max_multiplier);
+        self.largest_tier = self.largest_tier.max(run.largest_tier);
+        self.total_ticks += run.ticks;
+    }
+
+    pub fn total_merges(&self) -> usize {
+        self.merges.iter().sum()
+    }
+
+    pub fn pumpkins(&self) -> usize {
+        self.merges.get(BALL_ORDER.len() - 1).copied().unwrap_or(0)
+    }
+
+    /// `(name, value)` pairs, in the order they're shown and exported.
+    fn rows(&self) -> Vec<(String, String)> {
+        let mut rows = vec![
+            ("games_played".to_string(), self.games_played.to_string()),
+            ("best_score".to_string(), self.best_score.to_string()),
+            ("total_score".to_string(), self.total_score.to_string()),
+            ("total_drops".to_string(), self.total_drops.to_string()),
+            ("total_merges".to_string(), self.total_merges().to_string()),
+            ("pumpkins".to_string(), self.pumpkins().to_string()),
+            (
+                "best_multiplier".to_string(),
+                self.best_multiplier.to_string(),
+            ),
+            (
+                "largest_tier".to_string(),
+                BALL_ORDER[self.largest_tier.min(BALL_ORDER.len() - 1)].to_string(),
+            ),
+            ("total_seconds".to_string(), seconds(self.total_ticks)),
+        ];
+        for (size, count) in self.merges.iter().enumerate().skip(1) {
+            if let Some(name) = BALL_ORDER.get(size) {
+                rows.push((format!("merges_{}", name), count.to_string()));
+            }
+        }
+        rows
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("stat,value\n");
+        for (name, value) in self.rows() {
+            csv.push_str(&format!("{},{}\n", name, value));
+        }
+        csv
+    }
+}
+
+fn seconds(ticks: u64) -> String {
+    format!("{:.0}", ticks as f32 / SIM_HZ)
+}
+
+fn format_duration(ticks: u64) -> String {
+    let secs = (ticks as f32 / SIM_HZ) as u64;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+fn reset_run_stats(mut stats: ResMut<RunStats>) {
+    *stats = RunStats::default();
+}
+
+fn track_run_stats(
+    mut dropped_er: EventReader<BallDroppedEvent>,
+    mut merged_er: EventReader<BallsMergedEvent>,
+    mut stats: ResMut<RunStats>,
+    tick: Res<SimTick>,
+) {
+    for ev in dropped_er.iter() {
+        stats.dropped(ev.size);
+    }
+    for ev in merged_er.iter() {
+        stats.merged(ev.size, ev.multiplier);
+    }
+    stats.ticks = tick.0;
+}
+
+/// Whether a run in `mode` goes towards the lifetime totals. Tutorials and challenges start from
+/// boards set up for them, and runs changed from the dev console don't count either.
+fn counts_for_lifetime(mode: GameMode, console_used: bool) -> bool {
+    matches!(mode, GameMode::Classic | GameMode::Chaos) && !console_used
+}
+
+fn record_run(
+    mut lifetime: ResMut<LifetimeStats>,
+    run: Res<RunStats>,
+    score: Res<Score>,
+    replay: Res<Replay>,
+    console: Res<ConsoleUsed>,
+) {
+    if !counts_for_lifetime(replay.mode, console.0) {
+        return;
+    }
+    lifetime.record(&run, score.0);
+    storage::save(STORAGE_KEY, &*lifetime);
+}
+
+#[derive(Component)]
+pub struct StatsButton;
+
+#[derive(Component)]
+struct BackButton;
+
+#[derive(Component)]
+struct ExportButton;
+
+#[derive(Component)]
+struct ExportText;
+
+#[derive(Component)]
+struct StatsTag;
+
+fn stats_button(
+    button_q: Query<&Interaction, (Changed<Interaction>, With<StatsButton>)>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if button_q.iter().any(|i| *i == Interaction::Pressed) {
+        next_state.0 = Some(GameState::Stats);
+    }
+}
+
+fn back_button(
+    button_q: Query<&Interaction, (Changed<Interaction>, With<BackButton>)>,
+    keys: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keys.just_pressed(KeyCode::Escape) || button_q.iter().any(|i| *i == Interaction::Pressed) {
+        next_state.0 = Some(GameState::Splash);
+    }
+}
+
+fn export_button(
+    button_q: Query<&Interaction, (Changed<Interaction>, With<ExportButton>)>,
+    mut text_q: Query<&mut Text, With<ExportText>>,
+    lifetime: Res<LifetimeStats>,
+    locale: Res<Locale>,
+) {
+    if !button_q.iter().any(|i| *i == Interaction::Pressed) {
+        return;
+    }
+
+    let json = serde_json::to_string_pretty(&*lifetime).unwrap_or_default();
+    let saved: Vec<String> = [
+        storage::export("pumpkin-stats.json", &json),
+        storage::export("pumpkin-stats.csv", &lifetime.to_csv()),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    if let Ok(mut text) = text_q.get_single_mut() {
+        text.sections[0].value = if saved.is_empty() {
+            locale.get("export-failed")
+        } else {
+            locale.fmt("export-saved", &[("files", saved.join(", "))])
+        };
+    }
+}
+
+fn build_stats(
+    mut commands: Commands,
+    font: Res<CustomFont>,
+    locale: Res<Locale>,
+    lifetime: Res<LifetimeStats>,
+) {
+    let section = |value: String, font_size: f32, color: Color| TextSection {
+        value,
+        style: TextStyle {
+            font: font.0.clone_weak(),
+            font_size,
+            color,
+        },
+    };
+
+    let mut sections = vec![section(
+        format!("{}\n\n", locale.get("stats-heading")),
+        40.0,
+        Color::WHITE,
+    )];
+    let lines = [
+        ("stat-games-played", lifetime.games_played.to_string()),
+        ("stat-best-score", lifetime.best_score.to_string()),
+        ("stat-total-score", lifetime.total_score.to_string()),
+        ("stat-drops", lifetime.total_drops.to_string()),
+        ("stat-merges", lifetime.total_merges().to_string()),
+        ("stat-pumpkins", lifetime.pumpkins().to_string()),
+        ("stat-best-chain", format!("{}x", lifetime.best_multiplier)),
+        ("stat-time-played", format_duration(lifetime.total_ticks)),
+    ];
+    for (key, value) in lines {
+        sections.push(section(
+            format!("{}: ", locale.get(key)),
+            25.0,
+            Color::ORANGE,
+        ));
+        sections.push(section(format!("{}\n", value), 25.0, Color::WHITE));
+    }
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    flex_wrap: FlexWrap::Wrap,
+                    padding: UiRect::top(Val::Px(30.0)),
+                    ..default()
+                },
+                background_color: Color::rgb_u8(52, 52, 52).into(),
+                ..default()
+            },
+            StatsTag,
+            SafeArea::default(),
+        ))
+        .with_children(|root| {
+            root.spawn(
+                TextBundle::from_sections(sections).with_text_alignment(TextAlignment::Center),
+            );
+            root.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    flex_wrap: FlexWrap::Wrap,
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|button_box| {
+                let toggle = (Color::BLUE, Color::MIDNIGHT_BLUE);
+                spawn_button(
+                    button_box,
+                    &font,
+                    &locale,
+                    "export",
+                    toggle,
+                    150.0,
+                    ExportButton,
+                );
+                spawn_button(
+                    button_box, &font, &locale, "back", toggle, 150.0, BackButton,
+                );
+                button_box.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: font.0.clone_weak(),
+                            font_size: 20.0,
+                            color: Color::WHITE,
+                        },
+                    )
+                    .with_text_alignment(TextAlignment::Center)
+                    .with_style(Style {
+                        flex_basis: Val::Percent(100.0),
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    }),
+                    ExportText,
+                ));
+            });
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_run() -> RunStats {
+        let mut run = RunStats::default();
+        run.dropped(0);
+        run.dropped(2);
+        run.merged(1, 1);
+        run.merged(3, 4);
+        run.ticks = 600;
+        run
+    }
+
+    #[test]
+    fn runs_count_drops_merges_and_bests() {
+        let run = sample_run();
+        assert_eq!(run.drops, 2);
+        assert_eq!(run.merges[1], 1);
+        assert_eq!(run.merges[3], 1);
+        assert_eq!(run.max_multiplier, 4);
+        assert_eq!(run.largest_tier, 3);
+    }
+
+    #[test]
+    fn lifetime_totals_add_runs_up() {
+        let mut lifetime = LifetimeStats::default();
+        lifetime.record(&sample_run(), 300);
+        lifetime.record(&sample_run(), 100);
+
+        assert_eq!(lifetime.games_played, 2);
+        assert_eq!(lifetime.best_score, 300);
+        assert_eq!(lifetime.total_score, 400);
+        assert_eq!(lifetime.total_drops, 4);
+        assert_eq!(lifetime.total_merges(), 4);
+        assert_eq!(lifetime.best_multiplier, 4);
+        assert_eq!(lifetime.total_ticks, 1200);
+    }
+
+    #[test]
+    fn older_saves_make_room_for_every_size() {
+        let mut lifetime = LifetimeStats {
+            merges: vec![5],
+            ..default()
+        };
+        lifetime.record(&sample_run(), 0);
+        assert_eq!(lifetime.merges.len(), BALL_ORDER.len());
+        assert_eq!(lifetime.merges[0], 5);
+        assert_eq!(lifetime.merges[3], 1);
+    }
+
+    #[test]
+    fn only_untouched_classic_and_chaos_runs_count() {
+        assert!(counts_for_lifetime(GameMode::Classic, false));
+        assert!(counts_for_lifetime(GameMode::Chaos, false));
+        assert!(!counts_for_lifetime(GameMode::Tutorial, false));
+        assert!(!counts_for_lifetime(GameMode::Challenge, false));
+        assert!(!counts_for_lifetime(GameMode::Classic, true));
+        assert!(!counts_for_lifetime(GameMode::Chaos, true));
+    }
+}
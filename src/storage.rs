@@ -0,0 +1,126 @@
+//! Somewhere to keep things between sessions: `localStorage` on the web, JSON files in the
+//! user's data directory everywhere else.
+
+use bevy::log::warn;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Reads back whatever was last saved under `key`, or `None` if there's nothing usable.
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let raw = read(key)?;
+    match serde_json::from_str(&raw) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("ignoring unreadable {}: {}", key, e);
+            None
+        }
+    }
+}
+
+pub fn save<T: Serialize>(key: &str, value: &T) {
+    match serde_json::to_string(value) {
+        Ok(raw) => write(key, &raw),
+        Err(e) => warn!("couldn't serialise {}: {}", key, e),
+    }
+}
+
+/// Hands a file to the player: into their downloads folder natively, as a download on the web.
+/// Returns where it went, for telling them.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn export(file_name: &str, contents: &str) -> Option<String> {
+    let dir = dirs::download_dir().or_else(|| Some(dirs::data_dir()?.join("pumpkin-game")))?;
+    let _ = std::fs::create_dir_all(&dir);
+    let path = dir.join(file_name);
+    match std::fs::write(&path, contents) {
+        Ok(()) => Some(path.display().to_string()),
+        Err(e) => {
+            warn!("couldn't write {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn export(file_name: &str, contents: &str) -> Option<String> {
+    use wasm_bindgen::{JsCast, JsValue};
+
+    let parts = js_sys::Array::of1(&JsValue::from_str(contents));
+    let blob = web_sys::Blob::new_with_str_sequence(&parts).ok()?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).ok()?;
+    let link = web_sys::window()?
+        .document()?
+        .create_element("a")
+        .ok()?
+        .dyn_into::<web_sys::HtmlAnchorElement>()
+        .ok()?;
+    link.set_href(&url);
+    link.set_download(file_name);
+    link.click();
+    let _ = web_sys::Url::revoke_object_url(&url);
+    Some(file_name.to_string())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path(key: &str) -> Option<std::path::PathBuf> {
+    Some(
+        dirs::data_dir()?
+            .join("pumpkin-game")
+            .join(format!("{}.json", key)),
+    )
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read(key: &str) -> Option<String> {
+    std::fs::read_to_string(path(key)?).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write(key: &str, raw: &str) {
+    let Some(path) = path(key) else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    if let Err(e) = std::fs::write(&path, raw) {
+        warn!("couldn't write {}: {}", path.display(), e);
+    }
+}
+
+/// Forgets whatever was saved under `key`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn remove(key: &str) {
+    if let Some(path) = path(key) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read(key: &str) -> Option<String> {
+    local_storage()?
+        .get_item(&format!("pumpkin-game.{}", key))
+        .ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write(key: &str, raw: &str) {
+    if let Some(storage) = local_storage() {
+        if storage
+            .set_item(&format!("pumpkin-game.{}", key), raw)
+            .is_err()
+        {
+            warn!("couldn't save {}", key);
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn remove(key: &str) {
+    if let Some(storage) = local_storage() {
+        let _ = storage.remove_item(&format!("pumpkin-game.{}", key));
+    }
+}
@@ -0,0 +1,385 @@
+//! A first run that shows new players the ropes. It's a [`GameMode::Tutorial`] run, so the balls
+//! it talks about are the balls that come, with a callout by whatever each step is about. Play
+//! starts one until the player has finished or skipped it, and the menu can start one any time.
+
+use bevy::{prelude::*, window::PrimaryWindow};
+use pumpkin_game::{
+    replay::Replay,
+    sim::{
+        reset_run, BallDroppedEvent, BallsMergedEvent, GameMode, RunConfig, BOX_WIDTH, DEATH_LINE,
+    },
+};
+
+use crate::{
+    locale::Locale, resume, storage, CustomFont, FakeBall, GameState, RunningTag, ScoreTag,
+};
+
+const STORAGE_KEY: &str = "tutorial";
+
+const CALLOUT_WIDTH: f32 = 280.0;
+/// Drops to give the player at making a chain before moving on without one.
+const COMBO_DROPS: usize = 6;
+
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Tutorial {
+            done: storage::load(STORAGE_KEY).unwrap_or_default(),
+            ..default()
+        })
+        .add_systems(Update, tutorial_button)
+        .add_systems(OnEnter(GameState::Running), pick_mode.before(reset_run))
+        .add_systems(
+            OnEnter(GameState::Running),
+            start_tutorial.after(resume::restore_run),
+        )
+        .add_systems(OnExit(GameState::Running), stop_tutorial)
+        .add_systems(
+            Update,
+            (
+                advance_tutorial,
+                (update_callout, place_callout, skip_button, draw_death_line),
+            )
+                .chain()
+                .run_if(in_state(GameState::Running)),
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Drop,
+    Merge,
+    Combo,
+    DeathLine,
+    Done,
+}
+
+/// Where a step's callout points.
+enum Anchor {
+    FakeBall,
+    Score,
+    World(Vec2),
+}
+
+impl Step {
+    fn message(self) -> &'static str {
+        match self {
+            Step::Drop => "tutorial-drop",
+            Step::Merge => "tutorial-merge",
+            Step::Combo => "tutorial-combo",
+            Step::DeathLine => "tutorial-line",
+            Step::Done => "tutorial-done",
+        }
+    }
+
+    fn anchor(self) -> Anchor {
+        match self {
+            Step::Drop | Step::Merge => Anchor::FakeBall,
+            Step::Combo => Anchor::Score,
+            Step::DeathLine | Step::Done => Anchor::World(Vec2::new(0.0, DEATH_LINE)),
+        }
+    }
+
+    /// How long a step that isn't waiting on the player stays up.
+    fn duration(self) -> f32 {
+        match self {
+            Step::DeathLine => 5.0,
+            Step::Done => 3.0,
+            _ => 0.0,
+        }
+    }
+
+    fn next(self) -> Option<Step> {
+        match self {
+            Step::Drop => Some(Step::Merge),
+            Step::Merge => Some(Step::Combo),
+            Step::Combo => Some(Step::DeathLine),
+            Step::DeathLine => Some(Step::Done),
+            Step::Done => None,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Tutorial {
+    /// Finished or skipped at some point, so Play goes straight to a normal run.
+    done: bool,
+    /// Asked for from the menu, for the next run to start.
+    requested: bool,
+    /// Where the run in progress is up to, if it's a tutorial.
+    step: Option<Step>,
+    /// Drops since the current step started.
+    drops: usize,
+    timer: Timer,
+}
+
+impl Tutorial {
+    fn go_to(&mut self, step: Option<Step>) {
+        self.step = step;
+        self.drops = 0;
+        if let Some(step) = step {
+            self.timer = Timer::from_seconds(step.duration(), TimerMode::Once);
+        }
+    }
+
+    fn finish(&mut self) {
+        self.go_to(None);
+        self.done = true;
+        storage::save(STORAGE_KEY, &self.done);
+    }
+}
+
+#[derive(Component)]
+pub struct TutorialButton;
+
+#[derive(Component)]
+struct SkipButton;
+
+#[derive(Component)]
+struct Callout;
+
+#[derive(Component)]
+struct CalloutText;
+
+fn tutorial_button(
+    button_q: Query<&Interaction, (Changed<Interaction>, With<TutorialButton>)>,
+    mut tutorial: ResMut<Tutorial>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if button_q.iter().any(|i| *i == Interaction::Pressed) {
+        tutorial.requested = true;
+        next_state.0 = Some(GameState::Running);
+    }
+}
+
+/// Makes the run that's starting a tutorial if it was asked for, or if the player has never
+/// been through one.
+pub fn pick_mode(mut tutorial: ResMut<Tutorial>, mut config: ResMut<RunConfig>) {
+    let tutorial_run = std::mem::take(&mut tutorial.requested) || !tutorial.done;
+    config.mode = if tutorial_run {
+        GameMode::Tutorial
+    } else {
+        GameMode::Classic
+    };
+}
+
+fn start_tutorial(
+    mut tutorial: ResMut<Tutorial>,
+    mut commands: Commands,
+    config: Res<RunConfig>,
+    replay: Res<Replay>,
+    font: Res<CustomFont>,
+    locale: Res<Locale>,
+) {
+    // a continued run has already been dealt balls the steps wouldn't line up with
+    if config.mode != GameMode::Tutorial || !replay.drops.is_empty() {
+        tutorial.go_to(None);
+        return;
+    }
+    tutorial.go_to(Some(Step::Drop));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Px(CALLOUT_WIDTH),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    flex_wrap: FlexWrap::Wrap,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0.1, 0.1, 0.1, 0.85).into(),
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+            Callout,
+            RunningTag,
+        ))
+        .with_children(|callout| {
+            callout.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.0.clone_weak(),
+                        font_size: 22.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_text_alignment(TextAlignment::Center)
+                .with_style(Style {
+                    max_width: Val::Px(CALLOUT_WIDTH - 20.0),
+                    ..default()
+                }),
+                CalloutText,
+            ));
+            callout
+                .spawn((
+                    ButtonBundle {
+                        background_color: Color::PURPLE.into(),
+                        border_color: Color::INDIGO.into(),
+                        style: Style {
+                            width: Val::Px(100.0),
+                            height: Val::Px(40.0),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            border: UiRect::all(Val::Px(4.0)),
+                            margin: UiRect::top(Val::Px(10.0)),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    SkipButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        locale.get("skip"),
+                        TextStyle {
+                            font: font.0.clone_weak(),
+                            font_size: 24.0,
+                            color: Color::WHITE,
+                        },
+                    ));
+                });
+        });
+}
+
+/// Leaving a tutorial part way through doesn't count as having done it.
+fn stop_tutorial(mut tutorial: ResMut<Tutorial>) {
+    tutorial.go_to(None);
+}
+
+fn advance_tutorial(
+    mut tutorial: ResMut<Tutorial>,
+    mut dropped_er: EventReader<BallDroppedEvent>,
+    mut merged_er: EventReader<BallsMergedEvent>,
+    time: Res<Time>,
+) {
+    let Some(step) = tutorial.step else {
+        dropped_er.clear();
+        merged_er.clear();
+        return;
+    };
+
+    let dropped = dropped_er.iter().count();
+    let best_multiplier = merged_er.iter().map(|ev| ev.multiplier).max();
+    tutorial.drops += dropped;
+    tutorial.timer.tick(time.delta());
+
+    let finished = match step {
+        Step::Drop => dropped > 0,
+        Step::Merge => best_multiplier.is_some(),
+        Step::Combo => {
+            best_multiplier.is_some_and(|multiplier| multiplier >= 2)
+                || tutorial.drops >= COMBO_DROPS
+        }
+        Step::DeathLine | Step::Done => tutorial.timer.finished(),
+    };
+    if !finished {
+        return;
+    }
+
+    match step.next() {
+        Some(next) => tutorial.go_to(Some(next)),
+        None => tutorial.finish(),
+    }
+}
+
+fn update_callout(
+    tutorial: Res<Tutorial>,
+    callout_q: Query<Entity, With<Callout>>,
+    mut text_q: Query<&mut Text, With<CalloutText>>,
+    mut commands: Commands,
+    locale: Res<Locale>,
+) {
+    if !tutorial.is_changed() {
+        return;
+    }
+
+    let Some(step) = tutorial.step else {
+        for entity in callout_q.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    };
+
+    let value = locale.get(step.message());
+    for mut text in text_q.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+/// Keeps the callout just below what it's about, and on screen.
+fn place_callout(
+    tutorial: Res<Tutorial>,
+    mut callout_q: Query<&mut Style, With<Callout>>,
+    fake_ball_q: Query<&GlobalTransform, With<FakeBall>>,
+    score_q: Query<(&GlobalTransform, &Node), With<ScoreTag>>,
+    camera_q: Query<(&Camera, &GlobalTransform)>,
+    window_q: Query<&Window, With<PrimaryWindow>>,
+    ui_scale: Res<UiScale>,
+) {
+    let (Some(step), Ok(mut style), Ok(window)) = (
+        tutorial.step,
+        callout_q.get_single_mut(),
+        window_q.get_single(),
+    ) else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = camera_q.get_single() else {
+        return;
+    };
+
+    let on_screen = |world: Vec2| camera.world_to_viewport(camera_transform, world.extend(0.0));
+    // UI nodes are laid out in physical pixels
+    let physical = window.scale_factor() as f32;
+
+    // in logical pixels from the top left
+    let anchor = match step.anchor() {
+        Anchor::FakeBall => fake_ball_q
+            .get_single()
+            .ok()
+            .and_then(|transform| on_screen(transform.translation().truncate()))
+            // the ball is on the drop line, leave room for the biggest that comes up
+            .map(|point| point + Vec2::Y * 40.0),
+        Anchor::Score => score_q.get_single().ok().map(|(transform, node)| {
+            (transform.translation().truncate() + Vec2::Y * node.size().y / 2.0) / physical
+        }),
+        Anchor::World(point) => on_screen(point),
+    };
+    // between drops there's no ball to point at, so leave it where it was
+    let Some(anchor) = anchor else {
+        return;
+    };
+
+    let scale = ui_scale.scale as f32;
+    let max_left = (window.width() / scale - CALLOUT_WIDTH).max(0.0);
+    style.left = Val::Px((anchor.x / scale - CALLOUT_WIDTH / 2.0).clamp(0.0, max_left));
+    style.top = Val::Px(anchor.y / scale + 10.0);
+}
+
+fn skip_button(
+    button_q: Query<&Interaction, (Changed<Interaction>, With<SkipButton>)>,
+    mut tutorial: ResMut<Tutorial>,
+) {
+    if button_q.iter().any(|i| *i == Interaction::Pressed) {
+        tutorial.finish();
+    }
+}
+
+fn draw_death_line(tutorial: Res<Tutorial>, mut gizmos: Gizmos) {
+    if tutorial.step != Some(Step::DeathLine) {
+        return;
+    }
+
+    let half = BOX_WIDTH / 2.0;
+    gizmos.line_2d(
+        Vec2::new(-half, DEATH_LINE),
+        Vec2::new(half, DEATH_LINE),
+        Color::RED,
+    );
+}
@@ -0,0 +1,271 @@
+//! Little animations for the balls. The sim swaps two balls for a bigger one in a single step, so
+//! a merge leaves behind stand-ins for the two that pull together and vanish while the new ball
+//! pops in, and balls that land hard squash a little. Only sprites are touched, never colliders,
+//! and with reduced motion on there's none of it. How many can be going at once comes down with
+//! the [`EffectsBudget`].
+
+use std::f32::consts::TAU;
+
+use bevy::{prelude::*, transform::TransformSystem};
+use bevy_xpbd_2d::prelude::*;
+use pumpkin_game::sim::{BallSize, BallsMergedEvent, SimEntity, SimParams};
+
+use crate::{
+    accessibility::Accessibility, quality::EffectsBudget, BallAtlas, GameState, RunningTag,
+};
+
+const GATHER_SECS: f32 = 0.1;
+/// How big the stand-ins have shrunk to by the time they meet.
+const GATHER_SCALE: f32 = 0.6;
+const POP_SECS: f32 = 0.25;
+/// How big a new ball starts out.
+const POP_FROM: f32 = 0.4;
+/// How far past its size a new ball goes before settling, as the back easing's constant.
+const POP_OVERSHOOT: f32 = 1.7;
+const SQUASH_SECS: f32 = 0.2;
+/// Landings slower than this, along the contact, don't squash.
+const LANDING_SPEED: f32 = 3.0;
+/// How much a ball squashes per unit of speed over [`LANDING_SPEED`].
+const SQUASH_PER_SPEED: f32 = 0.02;
+const MAX_SQUASH: f32 = 0.15;
+/// How long a merge waits to find the ball it made.
+const PENDING_SECS: f32 = 0.1;
+/// Most stand-ins on screen at once, with the full effects budget.
+const MAX_GATHERS: usize = 16;
+/// Most squashes started in one frame, with the full effects budget.
+const MAX_SQUASHES: usize = 8;
+
+pub struct TweenPlugin;
+
+impl Plugin for TweenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (gather_merges, squash_landings).run_if(in_state(GameState::Running)),
+        )
+        // after the frame's commands, so a ball is never drawn at full size before it pops
+        .add_systems(
+            PostUpdate,
+            (run_ball_tweens, run_gathers).before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Tween {
+    /// Grows in from small, past full size and back.
+    Pop,
+    /// Flattens along `normal`, in world space, and bounces back out.
+    Squash { normal: Vec2, amount: f32 },
+}
+
+#[derive(Component)]
+struct BallTween {
+    tween: Tween,
+    timer: Timer,
+}
+
+/// A stand-in for a ball that merged, heading for where the new one is.
+#[derive(Component)]
+struct Gather {
+    from: Vec2,
+    to: Vec2,
+    timer: Timer,
+}
+
+/// Where and what size merges made balls that haven't shown up yet, with how long they've
+/// waited.
+#[derive(Default)]
+struct PendingMerges(Vec<(Vec2, usize, f32)>);
+
+#[allow(clippy::too_many_arguments)]
+fn gather_merges(
+    mut merged_er: EventReader<BallsMergedEvent>,
+    added_q: Query<(Entity, &Position, &BallSize), Added<SimEntity>>,
+    gather_q: Query<(), With<Gather>>,
+    mut pending: Local<PendingMerges>,
+    mut commands: Commands,
+    ball_atlas: Res<BallAtlas>,
+    params: Res<SimParams>,
+    settings: Res<Accessibility>,
+    budget: Res<EffectsBudget>,
+    time: Res<Time>,
+) {
+    if settings.reduced_motion {
+        merged_er.clear();
+        pending.0.clear();
+        return;
+    }
+
+    let mut gathers = gather_q.iter().count();
+    for ev in merged_er.iter() {
+        // the new ball still pops when there's no room for its stand-ins
+        pending.0.push((ev.position, ev.size, 0.0));
+        if gathers + ev.from.len() > budget.scale(MAX_GATHERS) {
+            continue;
+        }
+        gathers += ev.from.len();
+
+        let from_size = ev.size - 1;
+        for &(position, angle) in &ev.from {
+            commands.spawn((
+                SpriteSheetBundle {
+                    sprite: TextureAtlasSprite {
+                        index: from_size,
+                        custom_size: Some(Vec2::splat(params.radius(from_size) * 2.0)),
+                        ..default()
+                    },
+                    texture_atlas: ball_atlas.0.clone_weak(),
+                    // behind the ball they're turning into
+                    transform: Transform::from_translation(position.extend(-0.1))
+                        .with_rotation(Quat::from_rotation_z(angle)),
+                    ..default()
+                },
+                Gather {
+                    from: position,
+                    to: ev.position,
+                    timer: Timer::from_seconds(GATHER_SECS, TimerMode::Once),
+                },
+                RunningTag,
+            ));
+        }
+    }
+
+    for (entity, position, size) in added_q.iter() {
+        let found = pending.0.iter().position(|&(merged_at, merged_size, _)| {
+            merged_size == size.0 && merged_at.distance(position.0) < params.radius(size.0)
+        });
+        if let Some(index) = found {
+            pending.0.swap_remove(index);
+            commands.entity(entity).insert(BallTween {
+                tween: Tween::Pop,
+                timer: Timer::from_seconds(POP_SECS, TimerMode::Once),
+            });
+        }
+    }
+
+    let dt = time.delta_seconds();
+    pending.0.retain_mut(|(_, _, waited)| {
+        *waited += dt;
+        *waited < PENDING_SECS
+    });
+}
+
+fn squash_landings(
+    mut collision_er: EventReader<Collision>,
+    ball_q: Query<(&LinearVelocity, Option<&BallTween>), With<BallSize>>,
+    mut commands: Commands,
+    settings: Res<Accessibility>,
+    budget: Res<EffectsBudget>,
+) {
+    if settings.reduced_motion {
+        collision_er.clear();
+        return;
+    }
+
+    let mut squashes = budget.scale(MAX_SQUASHES);
+    for Collision(contact) in collision_er.iter() {
+        let velocity = |entity| ball_q.get(entity).map_or(Vec2::ZERO, |(v, _)| v.0);
+        let speed = (velocity(contact.entity1) - velocity(contact.entity2))
+            .dot(contact.normal)
+            .abs();
+        if speed < LANDING_SPEED {
+            continue;
+        }
+        let amount = ((speed - LANDING_SPEED) * SQUASH_PER_SPEED).min(MAX_SQUASH);
+
+        for entity in [contact.entity1, contact.entity2] {
+            let Ok((_, tween)) = ball_q.get(entity) else {
+                continue;
+            };
+            // a pop, or a harder squash, is already under way
+            let busy = tween.is_some_and(|tween| match tween.tween {
+                Tween::Pop => true,
+                Tween::Squash {
+                    amount: current, ..
+                } => current * tween.timer.percent_left() >= amount,
+            });
+            if !busy && squashes > 0 {
+                squashes -= 1;
+                commands.entity(entity).insert(BallTween {
+                    tween: Tween::Squash {
+                        normal: contact.normal,
+                        amount,
+                    },
+                    timer: Timer::from_seconds(SQUASH_SECS, TimerMode::Once),
+                });
+            }
+        }
+    }
+}
+
+fn run_ball_tweens(
+    mut ball_q: Query<(
+        Entity,
+        &mut BallTween,
+        &mut TextureAtlasSprite,
+        &Transform,
+        &BallSize,
+    )>,
+    mut commands: Commands,
+    params: Res<SimParams>,
+    time: Res<Time>,
+) {
+    for (entity, mut tween, mut sprite, transform, size) in ball_q.iter_mut() {
+        tween.timer.tick(time.delta());
+        let t = tween.timer.percent();
+
+        let scale = match tween.tween {
+            Tween::Pop => Vec2::splat(POP_FROM + (1.0 - POP_FROM) * ease_out_back(t)),
+            Tween::Squash { normal, amount } => {
+                // squashed at the moment of landing, then a little stretched, then round
+                let squash = amount * (1.0 - t) * (t * TAU).cos();
+                let along = 1.0 - squash;
+                let across = 1.0 / along;
+                // the sprite can only be sized along its own axes, which turn with the ball
+                let local = (transform.rotation.inverse() * normal.extend(0.0)).truncate();
+                let (x, y) = (local.x * local.x, local.y * local.y);
+                Vec2::new(
+                    1.0 + (along - 1.0) * x + (across - 1.0) * y,
+                    1.0 + (along - 1.0) * y + (across - 1.0) * x,
+                )
+            }
+        };
+
+        let full = Vec2::splat(params.radius(size.0) * 2.0);
+        if tween.timer.finished() {
+            sprite.custom_size = Some(full);
+            commands.entity(entity).remove::<BallTween>();
+        } else {
+            sprite.custom_size = Some(full * scale);
+        }
+    }
+}
+
+fn run_gathers(
+    mut gather_q: Query<(Entity, &mut Gather, &mut Transform, &mut TextureAtlasSprite)>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    for (entity, mut gather, mut transform, mut sprite) in gather_q.iter_mut() {
+        gather.timer.tick(time.delta());
+        if gather.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let t = gather.timer.percent();
+        let eased = t * t;
+        let position = gather.from.lerp(gather.to, eased);
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        transform.scale = Vec3::splat(1.0 + (GATHER_SCALE - 1.0) * eased);
+        sprite.color.set_a(1.0 - eased);
+    }
+}
+
+/// Eases out past 1 and back, for an overshoot.
+fn ease_out_back(t: f32) -> f32 {
+    let u = t - 1.0;
+    1.0 + (POP_OVERSHOOT + 1.0) * u * u * u + POP_OVERSHOOT * u * u
+}